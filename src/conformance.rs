@@ -0,0 +1,526 @@
+use crate::software::SoftwareSurface;
+use crate::surface::Surface;
+use nuklear::*;
+use std::f32::consts::PI;
+use std::fmt;
+
+/// Width and height of the canvas every case draws on.
+pub const SIZE: u16 = 64;
+
+const BACKGROUND: Color = Color { r: 0, g: 0, b: 0, a: 255 };
+const INK: Color = Color { r: 255, g: 255, b: 255, a: 255 };
+
+/// Gives access to what a surface has drawn.
+pub trait Readback {
+    /// RGBA of the pixel at (`x`, `y`).
+    fn read_pixel(&mut self, x: i32, y: i32) -> [u8; 4];
+}
+
+impl Readback for SoftwareSurface {
+    fn read_pixel(&mut self, x: i32, y: i32) -> [u8; 4] {
+        self.pixel(x, y)
+    }
+}
+
+/// A drawing run on both the surface under test and the reference `SoftwareSurface`.
+pub struct Case {
+    pub name: String,
+    pub draw: Box<dyn Fn(&mut dyn Surface)>,
+}
+
+impl Case {
+    pub fn new<F: Fn(&mut dyn Surface) + 'static>(name: &str, draw: F) -> Case {
+        Case { name: name.to_owned(), draw: Box::new(draw) }
+    }
+}
+
+/// A case whose output differs from the reference in more pixels than allowed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mismatch {
+    pub case: String,
+    pub pixels: usize,
+    /// Where the first differing pixel was found, scanning row by row.
+    pub first: (i32, i32),
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {} pixels differ, first at {},{}", self.case, self.pixels, self.first.0, self.first.1)
+    }
+}
+
+/// Filled and stroked arcs over a set of angle ranges: quadrants, sweeps crossing zero, negative sweeps, full and
+/// over-full turns, an empty sweep and start angles past a full turn.
+pub fn arc_cases() -> Vec<Case> {
+    let ranges: [(&str, f32, f32); 10] = [
+        ("quarter", 0.0, PI / 2.0),
+        ("half", PI / 2.0, PI * 1.5),
+        ("wedge", 0.1, 0.6),
+        ("across_zero", -PI / 4.0, PI / 4.0),
+        ("three_quarters", PI, PI * 2.5),
+        ("negative", PI, PI / 2.0),
+        ("negative_across_zero", PI / 4.0, -PI / 4.0),
+        ("full", 0.0, PI * 2.0),
+        ("over_full", 1.0, 1.0 + PI * 3.0),
+        ("empty", 1.0, 1.0),
+    ];
+
+    let mut cases = Vec::new();
+    for &(name, a1, a2) in &ranges {
+        cases.push(Case::new(&format!("fill_arc_{}", name), move |s: &mut dyn Surface| s.fill_arc(32, 32, 20, a1, a2, INK)));
+        cases.push(Case::new(&format!("stroke_arc_{}", name), move |s: &mut dyn Surface| s.stroke_arc(32, 32, 20, a1, a2, 1, INK)));
+    }
+    cases.push(Case::new("fill_arc_past_full_turn", |s: &mut dyn Surface| s.fill_arc(32, 32, 20, PI * 2.5, PI * 3.0, INK)));
+    cases
+}
+
+/// Filled and stroked rounded rectangles: no rounding, small and large radii, radii clamped by the shorter side and
+/// thick outlines, so corner size and fill/stroke coverage stay in step with the reference.
+pub fn round_rect_cases() -> Vec<Case> {
+    let rects: [(&str, i32, i32, i32, i32, i32); 6] = [
+        ("square", 8, 8, 40, 40, 0),
+        ("small", 8, 8, 40, 40, 4),
+        ("large", 8, 8, 40, 40, 12),
+        ("clamped", 8, 8, 40, 40, 100),
+        ("narrow", 8, 8, 10, 40, 8),
+        ("flat", 8, 8, 40, 9, 8),
+    ];
+
+    let mut cases = Vec::new();
+    for &(name, x, y, w, h, r) in &rects {
+        cases.push(Case::new(&format!("fill_rect_{}", name), move |s: &mut dyn Surface| s.fill_rect(x, y, w, h, r, INK)));
+        cases.push(Case::new(&format!("stroke_rect_{}", name), move |s: &mut dyn Surface| s.stroke_rect(x, y, w, h, r, 1, INK)));
+        cases.push(Case::new(&format!("stroke_rect_{}_thick", name), move |s: &mut dyn Surface| s.stroke_rect(x, y, w, h, r, 3, INK)));
+    }
+    cases
+}
+
+/// Thick lines, polylines and polygons under the default stroke style: miter joins and square caps.
+pub fn stroke_cases() -> Vec<Case> {
+    let paths: [(&str, &[(i16, i16)]); 4] = [
+        ("zigzag", &[(8, 48), (20, 16), (32, 48), (44, 16), (56, 48)]),
+        ("right_angle", &[(12, 12), (52, 12), (52, 52)]),
+        ("sharp", &[(8, 12), (56, 20), (8, 28)]),
+        ("reversal", &[(12, 32), (52, 32), (24, 32)]),
+    ];
+
+    let mut cases = Vec::new();
+    for &(name, path) in &paths {
+        let points: Vec<Vec2i> = path.iter().map(|&(x, y)| Vec2i { x: x, y: y }).collect();
+        let polygon = points.clone();
+        cases.push(Case::new(&format!("stroke_polyline_{}", name), move |s: &mut dyn Surface| s.stroke_polyline(&points, 5, INK)));
+        cases.push(Case::new(&format!("stroke_polygon_{}", name), move |s: &mut dyn Surface| s.stroke_polygon(&polygon, 5, INK)));
+    }
+    for &thickness in &[2, 3, 6] {
+        cases.push(Case::new(&format!("stroke_line_{}", thickness), move |s: &mut dyn Surface| s.stroke_line(10, 50, 50, 14, thickness, INK)));
+        cases.push(Case::new(&format!("stroke_triangle_{}", thickness), move |s: &mut dyn Surface| s.stroke_triangle(12, 52, 32, 10, 52, 52, thickness, INK)));
+    }
+    cases
+}
+
+/// A case with the pixels it must cover: everything drawn lies within `bounds` (`left, top, right, bottom`, right and bottom
+/// excluded) and touches all four of its edges. With `solid`, every pixel inside is drawn as well.
+pub struct BoundsCase {
+    pub case: Case,
+    pub bounds: (i32, i32, i32, i32),
+    pub solid: bool,
+}
+
+impl BoundsCase {
+    pub fn new<F: Fn(&mut dyn Surface) + 'static>(name: &str, bounds: (i32, i32, i32, i32), solid: bool, draw: F) -> BoundsCase {
+        BoundsCase {
+            case: Case::new(name, draw),
+            bounds: bounds,
+            solid: solid,
+        }
+    }
+}
+
+/// A case that drew outside its bounds, fell short of them or left a hole.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoundsMismatch {
+    pub case: String,
+    pub expected: (i32, i32, i32, i32),
+    /// Bounding box of what was drawn; `None` when nothing was.
+    pub actual: Option<(i32, i32, i32, i32)>,
+    /// Undrawn pixels inside `expected` of a solid case.
+    pub holes: usize,
+}
+
+impl fmt::Display for BoundsMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (l, t, r, b) = self.expected;
+        write!(f, "{}: expected {},{}..{},{}, ", self.case, l, t, r, b)?;
+        match self.actual {
+            Some((l, t, r, b)) => write!(f, "drawn {},{}..{},{}", l, t, r, b)?,
+            None => write!(f, "nothing drawn")?,
+        }
+        if self.holes > 0 {
+            write!(f, ", {} holes", self.holes)?;
+        }
+        Ok(())
+    }
+}
+
+/// Shapes whose footprint follows from the rounding policy on `Surface`: boxes cover `x..x + w` by `y..y + h` for every
+/// primitive, scissors clip to exactly their rect and boxes sharing an edge meet without a seam.
+pub fn bounds_cases() -> Vec<BoundsCase> {
+    let full = |s: &mut dyn Surface| s.fill_rect(0, 0, SIZE as i32, SIZE as i32, 0, INK);
+
+    vec![
+        BoundsCase::new("fill_rect", (10, 10, 30, 30), true, |s: &mut dyn Surface| s.fill_rect(10, 10, 20, 20, 0, INK)),
+        BoundsCase::new("fill_rect_rounded", (10, 10, 30, 30), false, |s: &mut dyn Surface| s.fill_rect(10, 10, 20, 20, 6, INK)),
+        BoundsCase::new("stroke_rect", (10, 10, 30, 30), false, |s: &mut dyn Surface| s.stroke_rect(10, 10, 20, 20, 0, 1, INK)),
+        BoundsCase::new("stroke_rect_rounded", (10, 10, 30, 30), false, |s: &mut dyn Surface| s.stroke_rect(10, 10, 20, 20, 6, 1, INK)),
+        BoundsCase::new("fill_circle", (10, 10, 30, 30), false, |s: &mut dyn Surface| s.fill_circle(10, 10, 20, 20, INK)),
+        BoundsCase::new("stroke_circle", (10, 10, 30, 30), false, |s: &mut dyn Surface| s.stroke_circle(10, 10, 20, 20, 1, INK)),
+        BoundsCase::new("fill_rect_one_pixel", (5, 7, 6, 8), true, |s: &mut dyn Surface| s.fill_rect(5, 7, 1, 1, 0, INK)),
+        BoundsCase::new("scissor", (10, 10, 30, 30), true, move |s: &mut dyn Surface| {
+            s.scissor(10, 10, 20, 20);
+            full(s);
+        }),
+        BoundsCase::new("adjacent_rects", (10, 10, 30, 30), true, |s: &mut dyn Surface| {
+            s.fill_rect(10, 10, 10, 20, 0, INK);
+            s.fill_rect(20, 10, 10, 20, 0, INK);
+        }),
+        BoundsCase::new("adjacent_scissors", (10, 10, 30, 30), true, move |s: &mut dyn Surface| {
+            s.scissor(10, 10, 20, 10);
+            full(s);
+            s.scissor(10, 20, 20, 10);
+            full(s);
+        }),
+    ]
+}
+
+/// Runs every case on `surface`, cleared to black first, and reports the ones whose drawn pixels do not match their bounds.
+/// `surface` must be at least `SIZE` by `SIZE`.
+pub fn check_bounds<S: Surface + Readback>(surface: &mut S, cases: &[BoundsCase]) -> Vec<BoundsMismatch> {
+    let mut mismatches = Vec::new();
+
+    for case in cases {
+        surface.clear(BACKGROUND);
+        (case.case.draw)(surface);
+
+        let (l, t, r, b) = case.bounds;
+        let mut actual: Option<(i32, i32, i32, i32)> = None;
+        let mut holes = 0;
+        for y in 0..SIZE as i32 {
+            for x in 0..SIZE as i32 {
+                let drawn = surface.read_pixel(x, y)[..3] != [BACKGROUND.r, BACKGROUND.g, BACKGROUND.b];
+                if drawn {
+                    actual = Some(match actual {
+                        Some((al, at, ar, ab)) => (al.min(x), at.min(y), ar.max(x + 1), ab.max(y + 1)),
+                        None => (x, y, x + 1, y + 1),
+                    });
+                } else if case.solid && x >= l && x < r && y >= t && y < b {
+                    holes += 1;
+                }
+            }
+        }
+
+        if actual != Some(case.bounds) || holes > 0 {
+            mismatches.push(BoundsMismatch {
+                case: case.case.name.clone(),
+                expected: case.bounds,
+                actual: actual,
+                holes: holes,
+            });
+        }
+    }
+    mismatches
+}
+
+/// Runs every case on `surface` and on a fresh `SoftwareSurface`, both cleared to black first, and reports the cases where
+/// more than `tolerance` pixels differ. `surface` must be at least `SIZE` by `SIZE`.
+pub fn check<S: Surface + Readback>(surface: &mut S, cases: &[Case], tolerance: usize) -> Vec<Mismatch> {
+    let mut mismatches = Vec::new();
+
+    for case in cases {
+        let mut reference = SoftwareSurface::new(SIZE, SIZE);
+        reference.clear(BACKGROUND);
+        (case.draw)(&mut reference);
+
+        surface.clear(BACKGROUND);
+        (case.draw)(surface);
+
+        let mut pixels = 0;
+        let mut first = None;
+        for y in 0..SIZE as i32 {
+            for x in 0..SIZE as i32 {
+                let (a, b) = (surface.read_pixel(x, y), reference.pixel(x, y));
+                if a[..3] != b[..3] {
+                    pixels += 1;
+                    first = first.or(Some((x, y)));
+                }
+            }
+        }
+
+        if pixels > tolerance {
+            mismatches.push(Mismatch {
+                case: case.name.clone(),
+                pixels: pixels,
+                first: first.unwrap_or((0, 0)),
+            });
+        }
+    }
+    mismatches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::surface::{LineCap, LineJoin};
+
+    fn draw<F: Fn(&mut dyn Surface)>(f: F) -> SoftwareSurface {
+        let mut surface = SoftwareSurface::new(SIZE, SIZE);
+        surface.clear(BACKGROUND);
+        f(&mut surface);
+        surface
+    }
+
+    fn drawn(surface: &SoftwareSurface, x: i32, y: i32) -> bool {
+        surface.pixel(x, y)[..3] != [BACKGROUND.r, BACKGROUND.g, BACKGROUND.b]
+    }
+
+    #[test]
+    fn software_quarter_arc_covers_its_quadrant_only() {
+        // angles grow clockwise on screen, so the first quadrant lies bottom right
+        let surface = draw(|s| s.fill_arc(32, 32, 20, 0.0, PI / 2.0, INK));
+        assert!(drawn(&surface, 40, 40));
+        assert!(!drawn(&surface, 24, 24));
+        assert!(!drawn(&surface, 40, 24));
+        assert!(!drawn(&surface, 24, 40));
+        assert!(!drawn(&surface, 50, 50));
+    }
+
+    #[test]
+    fn software_negative_arc_matches_its_positive_sweep() {
+        let negative = draw(|s| s.fill_arc(32, 32, 20, PI, PI / 2.0, INK));
+        let positive = draw(|s| s.fill_arc(32, 32, 20, PI / 2.0, PI, INK));
+        assert!(negative.pixels() == positive.pixels());
+        assert!(drawn(&negative, 24, 40));
+    }
+
+    #[test]
+    fn software_full_arc_is_a_circle() {
+        let circle = draw(|s| s.fill_circle(12, 12, 40, 40, INK));
+        for &(a1, a2) in &[(0.0, PI * 2.0), (1.0, 1.0 + PI * 3.0)] {
+            assert!(draw(|s| s.fill_arc(32, 32, 20, a1, a2, INK)).pixels() == circle.pixels());
+        }
+    }
+
+    #[test]
+    fn software_empty_arc_draws_nothing() {
+        let blank = draw(|_| {});
+        assert!(draw(|s| s.fill_arc(32, 32, 20, 1.0, 1.0, INK)).pixels() == blank.pixels());
+        assert!(draw(|s| s.stroke_arc(32, 32, 20, 1.0, 1.0, 1, INK)).pixels() == blank.pixels());
+    }
+
+    #[test]
+    fn software_quarter_arc_is_exactly_its_disc_quadrant() {
+        // the circle is centered on the corner shared by pixels (31, 31) and (32, 32)
+        let surface = draw(|s| s.fill_arc(32, 32, 20, 0.0, PI / 2.0, INK));
+        for y in 0..SIZE as i32 {
+            for x in 0..SIZE as i32 {
+                let (dx, dy) = (x as f32 - 31.5, y as f32 - 31.5);
+                assert_eq!(drawn(&surface, x, y), x >= 32 && y >= 32 && dx * dx + dy * dy <= 400.0, "pixel {},{}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn software_arc_edges_at_zero_and_ninety_degrees() {
+        // 0° runs right along row 32, 90° down along column 32; the rows and columns just across stay empty
+        let filled = draw(|s| s.fill_arc(32, 32, 20, 0.0, PI / 2.0, INK));
+        for i in 0..SIZE as i32 {
+            assert_eq!(drawn(&filled, i, 32), i >= 32 && i <= 51, "0° at {}", i);
+            assert_eq!(drawn(&filled, 32, i), i >= 32 && i <= 51, "90° at {}", i);
+            assert!(!drawn(&filled, i, 31) && !drawn(&filled, 31, i));
+        }
+
+        let stroked = draw(|s| s.stroke_arc(32, 32, 20, 0.0, PI / 2.0, 1, INK));
+        for i in 0..SIZE as i32 {
+            assert_eq!(drawn(&stroked, i, 32), i == 51, "0° at {}", i);
+            assert_eq!(drawn(&stroked, 32, i), i == 51, "90° at {}", i);
+        }
+    }
+
+    #[test]
+    fn software_arc_stops_at_its_end_angles() {
+        // a wedge from 45° to 135°; each pair straddles one end angle by about 10°
+        let surface = draw(|s| s.fill_arc(32, 32, 20, PI / 4.0, PI * 0.75, INK));
+        assert!(drawn(&surface, 40, 44) && !drawn(&surface, 44, 40));
+        assert!(drawn(&surface, 24, 44) && !drawn(&surface, 20, 40));
+        assert!(drawn(&surface, 32, 51) && !drawn(&surface, 32, 52));
+        assert!(!drawn(&surface, 32, 31));
+
+        let negative = draw(|s| s.fill_arc(32, 32, 20, PI * 0.75, PI / 4.0, INK));
+        assert!(negative.pixels() == surface.pixels());
+    }
+
+    #[test]
+    fn software_unrounded_rect_fills_its_box() {
+        let surface = draw(|s| s.fill_rect(8, 8, 40, 40, 0, INK));
+        for y in 0..SIZE as i32 {
+            for x in 0..SIZE as i32 {
+                assert_eq!(drawn(&surface, x, y), x >= 8 && x < 48 && y >= 8 && y < 48, "pixel {},{}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn software_corners_are_cut_at_the_radius() {
+        // corner circles of radius 12 centered on (20, 20), (36, 20), (20, 36) and (36, 36), sampled at pixel centers
+        let surface = draw(|s| s.fill_rect(8, 8, 40, 40, 12, INK));
+        for y in 0..SIZE as i32 {
+            for x in 0..SIZE as i32 {
+                let (qx, qy) = (x as f32 + 0.5, y as f32 + 0.5);
+                let (dx, dy) = ((20.0 - qx).max(qx - 36.0).max(0.0), (20.0 - qy).max(qy - 36.0).max(0.0));
+                assert_eq!(drawn(&surface, x, y), x >= 8 && x < 48 && y >= 8 && y < 48 && dx * dx + dy * dy <= 144.0, "pixel {},{}", x, y);
+            }
+        }
+
+        // along the diagonal the cut-out ends between (11, 11) and (12, 12); along the edge between (8, 16) and (8, 17)
+        assert!(!drawn(&surface, 11, 11) && drawn(&surface, 12, 12));
+        assert!(!drawn(&surface, 44, 44) && drawn(&surface, 43, 43));
+        assert!(!drawn(&surface, 8, 16) && drawn(&surface, 8, 17));
+        assert!(!drawn(&surface, 47, 39) && drawn(&surface, 47, 38));
+
+        let stroked = draw(|s| s.stroke_rect(8, 8, 40, 40, 12, 1, INK));
+        assert!(!drawn(&stroked, 8, 16) && drawn(&stroked, 8, 17) && !drawn(&stroked, 9, 17));
+    }
+
+    #[test]
+    fn software_radius_is_clamped_to_half_the_shorter_side() {
+        // 40x10 clamps any radius to 5, centered on (13, 13) for the top left corner
+        let clamped = draw(|s| s.fill_rect(8, 8, 40, 10, 100, INK));
+        assert!(clamped.pixels() == draw(|s| s.fill_rect(8, 8, 40, 10, 5, INK)).pixels());
+        for &(x, y) in &[(8, 8), (9, 8), (10, 8), (8, 9), (8, 10), (47, 17), (46, 17), (47, 15)] {
+            assert!(!drawn(&clamped, x, y), "pixel {},{}", x, y);
+        }
+        for &(x, y) in &[(11, 8), (9, 9), (8, 11), (8, 14), (28, 8), (44, 17), (47, 14)] {
+            assert!(drawn(&clamped, x, y), "pixel {},{}", x, y);
+        }
+    }
+
+    #[test]
+    fn bounds_cases_hold() {
+        assert_eq!(check_bounds(&mut SoftwareSurface::new(SIZE, SIZE), &bounds_cases()), vec![]);
+    }
+
+    #[test]
+    fn software_caps_follow_the_stroke_style() {
+        let mut surface = SoftwareSurface::new(SIZE, SIZE);
+        surface.clear(BACKGROUND);
+        surface.stroke_line(10, 32, 50, 32, 4, INK);
+        assert!(drawn(&surface, 8, 32) && drawn(&surface, 52, 32));
+        assert!(!drawn(&surface, 7, 32) && !drawn(&surface, 53, 32));
+
+        surface.set_stroke_style(LineJoin::Miter, LineCap::Flat);
+        surface.clear(BACKGROUND);
+        surface.stroke_line(10, 32, 50, 32, 4, INK);
+        assert!(drawn(&surface, 10, 32) && drawn(&surface, 50, 32));
+        assert!(!drawn(&surface, 9, 32) && !drawn(&surface, 51, 32));
+    }
+
+    #[test]
+    fn software_joins_follow_the_stroke_style() {
+        // the outer corner of a right angle turning at (52, 12) lies at (54, 10)
+        let corner = |join: LineJoin| {
+            let mut surface = SoftwareSurface::new(SIZE, SIZE);
+            surface.set_stroke_style(join, LineCap::Flat);
+            surface.clear(BACKGROUND);
+            surface.stroke_polyline(&[Vec2i { x: 12, y: 12 }, Vec2i { x: 52, y: 12 }, Vec2i { x: 52, y: 52 }], 4, INK);
+            drawn(&surface, 54, 10)
+        };
+        assert!(corner(LineJoin::Miter));
+        assert!(!corner(LineJoin::Bevel));
+        assert!(!corner(LineJoin::Round));
+    }
+
+    fn stroked<F: Fn(&mut SoftwareSurface)>(join: LineJoin, cap: LineCap, f: F) -> SoftwareSurface {
+        let mut surface = SoftwareSurface::new(SIZE, SIZE);
+        surface.set_stroke_style(join, cap);
+        surface.clear(BACKGROUND);
+        f(&mut surface);
+        surface
+    }
+
+    fn assert_covers<F: Fn(i32, i32) -> bool>(surface: &SoftwareSurface, expected: F) {
+        for y in 0..SIZE as i32 {
+            for x in 0..SIZE as i32 {
+                assert_eq!(drawn(surface, x, y), expected(x, y), "pixel {},{}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn software_thick_lines_cover_exact_boxes() {
+        // a 5 px pen spans 2.5 px to either side of the pixel centers it runs through; square caps reach as far past the ends
+        let horizontal = stroked(LineJoin::Miter, LineCap::Square, |s| s.stroke_line(10, 32, 50, 32, 5, INK));
+        assert_covers(&horizontal, |x, y| x >= 8 && x <= 52 && y >= 30 && y <= 34);
+        let vertical = stroked(LineJoin::Miter, LineCap::Square, |s| s.stroke_line(32, 10, 32, 50, 5, INK));
+        assert_covers(&vertical, |x, y| x >= 30 && x <= 34 && y >= 8 && y <= 52);
+
+        let flat = stroked(LineJoin::Miter, LineCap::Flat, |s| s.stroke_line(10, 32, 50, 32, 5, INK));
+        assert_covers(&flat, |x, y| x >= 10 && x <= 50 && y >= 30 && y <= 34);
+    }
+
+    #[test]
+    fn software_round_caps_cover_the_pixels_within_half_the_width() {
+        let surface = stroked(LineJoin::Miter, LineCap::Round, |s| s.stroke_line(10, 32, 50, 32, 5, INK));
+        assert_covers(&surface, |x, y| {
+            let (dy, end) = ((y - 32).abs(), if x < 10 { 10 - x } else if x > 50 { x - 50 } else { 0 });
+            dy <= 2 && end * end + dy * dy <= 6
+        });
+    }
+
+    #[test]
+    fn software_diagonal_lines_cover_their_band() {
+        // pixel centers clearly inside or outside the 6 px band around (10, 10)-(50, 50), flat ends
+        let surface = stroked(LineJoin::Miter, LineCap::Flat, |s| s.stroke_line(10, 10, 50, 50, 6, INK));
+        for y in 0..SIZE as i32 {
+            for x in 0..SIZE as i32 {
+                let across = (x - y) as f32 / 2f32.sqrt();
+                let along = (x + y - 20) as f32 / 2f32.sqrt();
+                let inside = across.abs() < 2.9 && along > 0.1 && along < 40.0 * 2f32.sqrt() - 0.1;
+                let outside = across.abs() > 3.1 || along < -0.1 || along > 40.0 * 2f32.sqrt() + 0.1;
+                assert!(!inside || drawn(&surface, x, y), "pixel {},{} not drawn", x, y);
+                assert!(!outside || !drawn(&surface, x, y), "pixel {},{} drawn", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn software_joins_cover_exact_corners() {
+        // a 5 px right angle turning at (52, 12): the legs cover rows 10..=14 up to column 52 and columns 50..=54 from row 12,
+        // leaving the 2x2 block at (53..=54, 10..=11) to the join
+        let legs = |x: i32, y: i32| (x >= 12 && x <= 52 && y >= 10 && y <= 14) || (x >= 50 && x <= 54 && y >= 12 && y <= 51);
+        let corner = |join: LineJoin| stroked(join, LineCap::Flat, |s| s.stroke_polyline(&[Vec2i { x: 12, y: 12 }, Vec2i { x: 52, y: 12 }, Vec2i { x: 52, y: 52 }], 5, INK));
+
+        assert_covers(&corner(LineJoin::Miter), |x, y| legs(x, y) || (x >= 53 && x <= 54 && y >= 10 && y <= 11));
+        assert_covers(&corner(LineJoin::Bevel), |x, y| legs(x, y) || (x, y) == (53, 11));
+        assert_covers(&corner(LineJoin::Round), |x, y| legs(x, y) || [(53, 10), (53, 11), (54, 11)].contains(&(x, y)));
+    }
+
+    #[test]
+    fn software_sharp_miters_reach_their_tip() {
+        // a 19° turn at (56, 20): its miter, about 6 widths long, runs off the canvas while the bevel stays behind x = 57
+        let path = [Vec2i { x: 8, y: 12 }, Vec2i { x: 56, y: 20 }, Vec2i { x: 8, y: 28 }];
+        let miter = stroked(LineJoin::Miter, LineCap::Flat, |s| s.stroke_polyline(&path, 5, INK));
+        let bevel = stroked(LineJoin::Bevel, LineCap::Flat, |s| s.stroke_polyline(&path, 5, INK));
+        for x in 57..SIZE as i32 {
+            assert!(drawn(&miter, x, 20), "pixel {},20", x);
+            for y in 0..SIZE as i32 {
+                assert!(!drawn(&bevel, x, y), "pixel {},{}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn software_reversals_are_beveled_flat() {
+        // turning straight back exceeds any miter limit, so nothing sticks out past the turn at x = 52
+        let surface = stroked(LineJoin::Miter, LineCap::Flat, |s| s.stroke_polyline(&[Vec2i { x: 12, y: 32 }, Vec2i { x: 52, y: 32 }, Vec2i { x: 24, y: 32 }], 5, INK));
+        assert_covers(&surface, |x, y| x >= 12 && x <= 52 && y >= 30 && y <= 34);
+    }
+}
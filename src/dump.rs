@@ -0,0 +1,207 @@
+use crate::frame::{Frame, FrameCommand, Point, Rgba};
+use nuklear::*;
+use std::fmt::Write;
+
+/// Dumps the commands queued in `ctx` without consuming them, so it can run right before `render`.
+pub fn dump<F: Fn(&UserFont) -> u32>(ctx: &mut Context, font_id: F) -> String {
+    dump_frame(&Frame::capture(ctx, font_id))
+}
+
+/// One line per command: type, geometry, colors as `#rrggbbaa`, thickness, font id and text.
+/// Image handles and custom user data differ from run to run, so they are shown as `@n`, numbered in order of first
+/// appearance in the frame, which keeps dumps comparable across runs.
+pub fn dump_frame(frame: &Frame) -> String {
+    let mut out = String::new();
+    let mut handles = Handles::default();
+    for cmd in &frame.commands {
+        dump_command(&mut out, &mut handles, cmd);
+        out.push('\n');
+    }
+    out
+}
+
+fn hex(c: Rgba) -> String {
+    format!("#{:02x}{:02x}{:02x}{:02x}", c[0], c[1], c[2], c[3])
+}
+
+fn pts(points: &[Point]) -> String {
+    points.iter().map(|p| format!("{},{}", p.0, p.1)).collect::<Vec<_>>().join(" ")
+}
+
+#[derive(Default)]
+struct Handles {
+    images: Vec<u64>,
+    data: Vec<u64>,
+}
+
+fn ordinal(seen: &mut Vec<u64>, value: u64) -> usize {
+    match seen.iter().position(|&v| v == value) {
+        Some(i) => i,
+        None => {
+            seen.push(value);
+            seen.len() - 1
+        }
+    }
+}
+
+fn dump_command(out: &mut String, handles: &mut Handles, cmd: &FrameCommand) {
+    let _ = match *cmd {
+        FrameCommand::Scissor { x, y, w, h } => write!(out, "scissor rect={},{},{},{}", x, y, w, h),
+        FrameCommand::Line { begin, end, line_thickness, color } => write!(out, "line points={} color={} thickness={}", pts(&[begin, end]), hex(color), line_thickness),
+        FrameCommand::Curve { begin, ctrl, end, line_thickness, color } => write!(out, "curve points={} color={} thickness={}", pts(&[begin, ctrl[0], ctrl[1], end]), hex(color), line_thickness),
+        FrameCommand::Rect { x, y, w, h, rounding, line_thickness, color } => write!(out, "rect rect={},{},{},{} rounding={} color={} thickness={}", x, y, w, h, rounding, hex(color), line_thickness),
+        FrameCommand::RectFilled { x, y, w, h, rounding, color } => write!(out, "rect_filled rect={},{},{},{} rounding={} color={}", x, y, w, h, rounding, hex(color)),
+        FrameCommand::RectMultiColor { x, y, w, h, left, top, right, bottom } => write!(
+            out,
+            "rect_multi_color rect={},{},{},{} left={} top={} right={} bottom={}",
+            x,
+            y,
+            w,
+            h,
+            hex(left),
+            hex(top),
+            hex(right),
+            hex(bottom)
+        ),
+        FrameCommand::Circle { x, y, w, h, line_thickness, color } => write!(out, "circle rect={},{},{},{} color={} thickness={}", x, y, w, h, hex(color), line_thickness),
+        FrameCommand::CircleFilled { x, y, w, h, color } => write!(out, "circle_filled rect={},{},{},{} color={}", x, y, w, h, hex(color)),
+        FrameCommand::Arc { cx, cy, r, a, line_thickness, color } => write!(out, "arc center={},{} r={} angles={:.4},{:.4} color={} thickness={}", cx, cy, r, a[0], a[1], hex(color), line_thickness),
+        FrameCommand::ArcFilled { cx, cy, r, a, color } => write!(out, "arc_filled center={},{} r={} angles={:.4},{:.4} color={}", cx, cy, r, a[0], a[1], hex(color)),
+        FrameCommand::Triangle { a, b, c, line_thickness, color } => write!(out, "triangle points={} color={} thickness={}", pts(&[a, b, c]), hex(color), line_thickness),
+        FrameCommand::TriangleFilled { a, b, c, color } => write!(out, "triangle_filled points={} color={}", pts(&[a, b, c]), hex(color)),
+        FrameCommand::Polygon { ref points, line_thickness, color } => write!(out, "polygon points={} color={} thickness={}", pts(points), hex(color), line_thickness),
+        FrameCommand::PolygonFilled { ref points, color } => write!(out, "polygon_filled points={} color={}", pts(points), hex(color)),
+        FrameCommand::Polyline { ref points, line_thickness, color } => write!(out, "polyline points={} color={} thickness={}", pts(points), hex(color), line_thickness),
+        FrameCommand::Text {
+            x,
+            y,
+            w,
+            h,
+            font,
+            height,
+            background,
+            foreground,
+            ref text,
+        } => write!(
+            out,
+            "text rect={},{},{},{} font={} height={} background={} color={} text={:?}",
+            x,
+            y,
+            w,
+            h,
+            font,
+            height,
+            hex(background),
+            hex(foreground),
+            String::from_utf8_lossy(text)
+        ),
+        FrameCommand::Image { x, y, w, h, image, color } => write!(out, "image rect={},{},{},{} image=@{} color={}", x, y, w, h, ordinal(&mut handles.images, image), hex(color)),
+        FrameCommand::Custom { x, y, w, h, data } => write!(out, "custom rect={},{},{},{} data=@{}", x, y, w, h, ordinal(&mut handles.data, data)),
+    };
+}
+
+/// Line diff of two dumps: unchanged lines start with `"  "`, removed with `"- "`, added with `"+ "`.
+/// Returns an empty string when the dumps are equal.
+pub fn diff(old: &str, new: &str) -> String {
+    if old == new {
+        return String::new();
+    }
+
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+
+    // frames mostly change in a few places, so the unchanged head and tail skip the comparison altogether
+    let head = a.iter().zip(&b).take_while(|&(x, y)| x == y).count();
+    let tail = a[head..].iter().rev().zip(b[head..].iter().rev()).take_while(|&(x, y)| x == y).count();
+
+    let mut out = String::new();
+    for line in &a[..head] {
+        let _ = writeln!(out, "  {}", line);
+    }
+    diff_lines(&mut out, &a[head..a.len() - tail], &b[head..b.len() - tail]);
+    for line in &a[a.len() - tail..] {
+        let _ = writeln!(out, "  {}", line);
+    }
+    out
+}
+
+// Hirschberg's divide and conquer: the split point of `b` for the middle of `a` comes from two rows of LCS lengths,
+// so memory stays linear in the dump size.
+fn diff_lines(out: &mut String, a: &[&str], b: &[&str]) {
+    if a.is_empty() || b.is_empty() {
+        for line in a {
+            let _ = writeln!(out, "- {}", line);
+        }
+        for line in b {
+            let _ = writeln!(out, "+ {}", line);
+        }
+        return;
+    }
+
+    if a.len() == 1 {
+        match b.iter().position(|&line| line == a[0]) {
+            Some(k) => {
+                diff_lines(out, &[], &b[..k]);
+                let _ = writeln!(out, "  {}", a[0]);
+                diff_lines(out, &[], &b[k + 1..]);
+            }
+            None => {
+                diff_lines(out, a, &[]);
+                diff_lines(out, &[], b);
+            }
+        }
+        return;
+    }
+
+    let mid = a.len() / 2;
+    let front = lcs_lengths(&a[..mid], b);
+    let back = lcs_lengths(&a[mid..].iter().rev().cloned().collect::<Vec<_>>(), &b.iter().rev().cloned().collect::<Vec<_>>());
+    let split = (0..=b.len()).max_by_key(|&k| front[k] + back[b.len() - k]).unwrap_or(0);
+    diff_lines(out, &a[..mid], &b[..split]);
+    diff_lines(out, &a[mid..], &b[split..]);
+}
+
+// `row[j]` is the length of the longest common subsequence of `a` and the first `j` lines of `b`.
+fn lcs_lengths(a: &[&str], b: &[&str]) -> Vec<usize> {
+    let mut prev = vec![0; b.len() + 1];
+    let mut row = vec![0; b.len() + 1];
+    for x in a {
+        for (j, y) in b.iter().enumerate() {
+            row[j + 1] = if x == y { prev[j] + 1 } else { prev[j + 1].max(row[j]) };
+        }
+        ::std::mem::swap(&mut prev, &mut row);
+    }
+    prev
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn handles_are_dumped_as_ordinals_of_first_appearance() {
+        let image = |image| FrameCommand::Image { x: 0, y: 0, w: 1, h: 1, image: image, color: [0; 4] };
+        let frame = Frame {
+            commands: vec![image(0xdead), image(0xbeef), image(0xdead), FrameCommand::Custom { x: 0, y: 0, w: 1, h: 1, data: 0xbeef }],
+        };
+
+        let dump = dump_frame(&frame);
+        let handles: Vec<&str> = dump.lines().map(|line| line.split(' ').find(|f| f.contains('@')).unwrap()).collect();
+        assert_eq!(handles, vec!["image=@0", "image=@1", "image=@0", "data=@0"]);
+    }
+
+    #[test]
+    fn equal_dumps_have_an_empty_diff() {
+        assert_eq!(diff("a\nb\n", "a\nb\n"), "");
+    }
+
+    #[test]
+    fn a_changed_line_is_removed_and_added() {
+        assert_eq!(diff("a\nb\nc\n", "a\nx\nc\n"), "  a\n- b\n+ x\n  c\n");
+    }
+
+    #[test]
+    fn inserted_and_removed_lines_keep_the_rest_unchanged() {
+        assert_eq!(diff("a\nc\nd\ne\n", "a\nb\nc\ne\n"), "  a\n+ b\n  c\n- d\n  e\n");
+    }
+}
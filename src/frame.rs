@@ -0,0 +1,789 @@
+use crate::surface::Surface;
+use nuklear::nuklear_sys as nksys;
+use nuklear::*;
+use std::io::{self, Read, Write};
+use std::slice;
+
+pub const MAGIC: [u8; 4] = *b"NKGF";
+pub const VERSION: u16 = 1;
+
+pub type Point = (i16, i16);
+pub type Rgba = [u8; 4];
+
+/// An owned copy of one nuklear command with all of its fields.
+///
+/// Fonts are stored as ids handed out by the capturing side; images and custom user data keep the raw value of their
+/// handle, which means nothing outside the capturing process.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FrameCommand {
+    Scissor { x: i16, y: i16, w: u16, h: u16 },
+    Line { begin: Point, end: Point, line_thickness: u16, color: Rgba },
+    Curve { begin: Point, ctrl: [Point; 2], end: Point, line_thickness: u16, color: Rgba },
+    Rect { x: i16, y: i16, w: u16, h: u16, rounding: u16, line_thickness: u16, color: Rgba },
+    RectFilled { x: i16, y: i16, w: u16, h: u16, rounding: u16, color: Rgba },
+    RectMultiColor { x: i16, y: i16, w: u16, h: u16, left: Rgba, top: Rgba, right: Rgba, bottom: Rgba },
+    Circle { x: i16, y: i16, w: u16, h: u16, line_thickness: u16, color: Rgba },
+    CircleFilled { x: i16, y: i16, w: u16, h: u16, color: Rgba },
+    Arc { cx: i16, cy: i16, r: u16, a: [f32; 2], line_thickness: u16, color: Rgba },
+    ArcFilled { cx: i16, cy: i16, r: u16, a: [f32; 2], color: Rgba },
+    Triangle { a: Point, b: Point, c: Point, line_thickness: u16, color: Rgba },
+    TriangleFilled { a: Point, b: Point, c: Point, color: Rgba },
+    Polygon { points: Vec<Point>, line_thickness: u16, color: Rgba },
+    PolygonFilled { points: Vec<Point>, color: Rgba },
+    Polyline { points: Vec<Point>, line_thickness: u16, color: Rgba },
+    Text { x: i16, y: i16, w: u16, h: u16, font: u32, height: f32, background: Rgba, foreground: Rgba, text: Vec<u8> },
+    Image { x: i16, y: i16, w: u16, h: u16, image: u64, color: Rgba },
+    Custom { x: i16, y: i16, w: u16, h: u16, data: u64 },
+}
+
+/// A recorded frame that can be saved, loaded and replayed without a live `Context`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Frame {
+    pub commands: Vec<FrameCommand>,
+}
+
+fn rgba(c: Color) -> Rgba {
+    [c.r, c.g, c.b, c.a]
+}
+
+fn color(c: Rgba) -> Color {
+    Color { r: c[0], g: c[1], b: c[2], a: c[3] }
+}
+
+fn point(p: Vec2i) -> Point {
+    (p.x as i16, p.y as i16)
+}
+
+fn vec2i(p: Point) -> Vec2i {
+    Vec2i { x: p.0, y: p.1 }
+}
+
+fn points(p: &[Vec2i]) -> Vec<Point> {
+    p.iter().map(|p| point(*p)).collect()
+}
+
+impl FrameCommand {
+    /// Copies a live command. `font_id` maps the command's font to the id stored in the frame.
+    pub fn from_command<F: Fn(&UserFont) -> u32>(cmd: &Command, font_id: F) -> Option<FrameCommand> {
+        let command = match cmd.get_type() {
+            CommandType::Scissor => {
+                let s: &CommandScissor = cmd.as_ref();
+                FrameCommand::Scissor { x: s.x() as i16, y: s.y() as i16, w: s.w() as u16, h: s.h() as u16 }
+            }
+            CommandType::Line => {
+                let l: &CommandLine = cmd.as_ref();
+                FrameCommand::Line { begin: point(l.begin()), end: point(l.end()), line_thickness: l.line_thickness() as u16, color: rgba(l.color()) }
+            }
+            CommandType::Curve => {
+                let q: &CommandCurve = cmd.as_ref();
+                FrameCommand::Curve {
+                    begin: point(q.begin()),
+                    ctrl: [point(q.ctrl()[0]), point(q.ctrl()[1])],
+                    end: point(q.end()),
+                    line_thickness: q.line_thickness() as u16,
+                    color: rgba(q.color()),
+                }
+            }
+            CommandType::Rect => {
+                let r: &CommandRect = cmd.as_ref();
+                FrameCommand::Rect {
+                    x: r.x() as i16,
+                    y: r.y() as i16,
+                    w: r.w() as u16,
+                    h: r.h() as u16,
+                    rounding: r.rounding() as u16,
+                    line_thickness: r.line_thickness() as u16,
+                    color: rgba(r.color()),
+                }
+            }
+            CommandType::RectFilled => {
+                let r: &CommandRectFilled = cmd.as_ref();
+                FrameCommand::RectFilled { x: r.x() as i16, y: r.y() as i16, w: r.w() as u16, h: r.h() as u16, rounding: r.rounding() as u16, color: rgba(r.color()) }
+            }
+            CommandType::RectMultiColor => {
+                let r: &CommandRectMultiColor = cmd.as_ref();
+                FrameCommand::RectMultiColor {
+                    x: r.x() as i16,
+                    y: r.y() as i16,
+                    w: r.w() as u16,
+                    h: r.h() as u16,
+                    left: rgba(r.left()),
+                    top: rgba(r.top()),
+                    right: rgba(r.right()),
+                    bottom: rgba(r.bottom()),
+                }
+            }
+            CommandType::Circle => {
+                let c: &CommandCircle = cmd.as_ref();
+                FrameCommand::Circle { x: c.x() as i16, y: c.y() as i16, w: c.w() as u16, h: c.h() as u16, line_thickness: c.line_thickness() as u16, color: rgba(c.color()) }
+            }
+            CommandType::CircleFilled => {
+                let c: &CommandCircleFilled = cmd.as_ref();
+                FrameCommand::CircleFilled { x: c.x() as i16, y: c.y() as i16, w: c.w() as u16, h: c.h() as u16, color: rgba(c.color()) }
+            }
+            CommandType::Arc => {
+                let a: &CommandArc = cmd.as_ref();
+                FrameCommand::Arc { cx: a.cx() as i16, cy: a.cy() as i16, r: a.r() as u16, a: [a.a()[0], a.a()[1]], line_thickness: a.line_thickness() as u16, color: rgba(a.color()) }
+            }
+            CommandType::ArcFilled => {
+                let a: &CommandArcFilled = cmd.as_ref();
+                FrameCommand::ArcFilled { cx: a.cx() as i16, cy: a.cy() as i16, r: a.r() as u16, a: [a.a()[0], a.a()[1]], color: rgba(a.color()) }
+            }
+            CommandType::Triangle => {
+                let t: &CommandTriangle = cmd.as_ref();
+                FrameCommand::Triangle { a: point(t.a()), b: point(t.b()), c: point(t.c()), line_thickness: t.line_thickness() as u16, color: rgba(t.color()) }
+            }
+            CommandType::TriangleFilled => {
+                let t: &CommandTriangleFilled = cmd.as_ref();
+                FrameCommand::TriangleFilled { a: point(t.a()), b: point(t.b()), c: point(t.c()), color: rgba(t.color()) }
+            }
+            CommandType::Polygon => {
+                let p: &CommandPolygon = cmd.as_ref();
+                FrameCommand::Polygon { points: points(p.points()), line_thickness: p.line_thickness() as u16, color: rgba(p.color()) }
+            }
+            CommandType::PolygonFilled => {
+                let p: &CommandPolygonFilled = cmd.as_ref();
+                FrameCommand::PolygonFilled { points: points(p.points()), color: rgba(p.color()) }
+            }
+            CommandType::Polyline => {
+                let p: &CommandPolyline = cmd.as_ref();
+                FrameCommand::Polyline { points: points(p.points()), line_thickness: p.line_thickness() as u16, color: rgba(p.color()) }
+            }
+            CommandType::Text => {
+                let t: &CommandText = cmd.as_ref();
+                let text = unsafe { slice::from_raw_parts(t.chars().as_ptr() as *const u8, t.chars().len()) };
+                FrameCommand::Text {
+                    x: t.x() as i16,
+                    y: t.y() as i16,
+                    w: t.w() as u16,
+                    h: t.h() as u16,
+                    font: font_id(t.font()),
+                    height: t.height(),
+                    background: rgba(t.background()),
+                    foreground: rgba(t.foreground()),
+                    text: text.to_vec(),
+                }
+            }
+            CommandType::Image => {
+                let i: &CommandImage = cmd.as_ref();
+                let mut img = i.img();
+                FrameCommand::Image { x: i.x() as i16, y: i.y() as i16, w: i.w() as u16, h: i.h() as u16, image: img.ptr() as usize as u64, color: rgba(i.col()) }
+            }
+            CommandType::Custom => {
+                let c = unsafe { &*(cmd as *const _ as *const nksys::nk_command_custom) };
+                FrameCommand::Custom { x: c.x, y: c.y, w: c.w, h: c.h, data: unsafe { c.callback_data.ptr } as usize as u64 }
+            }
+            _ => return None,
+        };
+        Some(command)
+    }
+
+    /// Draws the command onto `surface`. Text is skipped when `font` cannot resolve its font id and images when `image`
+    /// cannot map the recorded handle value to a live image. Custom commands are never dispatched: their user data
+    /// is a pointer into the recording process.
+    pub fn draw<'a, S: Surface + ?Sized, F: Fn(u32) -> Option<&'a UserFont>, I: Fn(u64) -> Option<Image>>(&self, surface: &mut S, font: F, image: I) {
+        surface.begin_command();
+
+        match *self {
+            FrameCommand::Scissor { x, y, w, h } => surface.scissor(x as i32, y as i32, w as i32, h as i32),
+            FrameCommand::Line { begin, end, line_thickness, color: c } => surface.stroke_line(begin.0 as i32, begin.1 as i32, end.0 as i32, end.1 as i32, line_thickness as i32, color(c)),
+            FrameCommand::Curve { begin, ctrl, end, line_thickness, color: c } => surface.stroke_curve(vec2i(begin), vec2i(ctrl[0]), vec2i(ctrl[1]), vec2i(end), line_thickness as i32, color(c)),
+            FrameCommand::Rect { x, y, w, h, rounding, line_thickness, color: c } => surface.stroke_rect(x as i32, y as i32, w as i32, h as i32, rounding as i32, line_thickness as i32, color(c)),
+            FrameCommand::RectFilled { x, y, w, h, rounding, color: c } => surface.fill_rect(x as i32, y as i32, w as i32, h as i32, rounding as i32, color(c)),
+            FrameCommand::RectMultiColor { x, y, w, h, left, top, right, bottom } => surface.fill_rect_multi_color(x as i32, y as i32, w as i32, h as i32, color(left), color(top), color(right), color(bottom)),
+            FrameCommand::Circle { x, y, w, h, line_thickness, color: c } => surface.stroke_circle(x as i32, y as i32, w as i32, h as i32, line_thickness as i32, color(c)),
+            FrameCommand::CircleFilled { x, y, w, h, color: c } => surface.fill_circle(x as i32, y as i32, w as i32, h as i32, color(c)),
+            FrameCommand::Arc { cx, cy, r, a, line_thickness, color: c } => surface.stroke_arc(cx as i32, cy as i32, r as u32, a[0], a[1], line_thickness as i32, color(c)),
+            FrameCommand::ArcFilled { cx, cy, r, a, color: c } => surface.fill_arc(cx as i32, cy as i32, r as u32, a[0], a[1], color(c)),
+            FrameCommand::Triangle { a, b, c, line_thickness, color: col } => surface.stroke_triangle(a.0 as i32, a.1 as i32, b.0 as i32, b.1 as i32, c.0 as i32, c.1 as i32, line_thickness as i32, color(col)),
+            FrameCommand::TriangleFilled { a, b, c, color: col } => surface.fill_triangle(a.0 as i32, a.1 as i32, b.0 as i32, b.1 as i32, c.0 as i32, c.1 as i32, color(col)),
+            FrameCommand::Polygon { ref points, line_thickness, color: c } => {
+                let points: Vec<Vec2i> = points.iter().map(|p| vec2i(*p)).collect();
+                surface.stroke_polygon(&points, line_thickness as i32, color(c));
+            }
+            FrameCommand::PolygonFilled { ref points, color: c } => {
+                let points: Vec<Vec2i> = points.iter().map(|p| vec2i(*p)).collect();
+                surface.fill_polygon(&points, color(c));
+            }
+            FrameCommand::Polyline { ref points, line_thickness, color: c } => {
+                let points: Vec<Vec2i> = points.iter().map(|p| vec2i(*p)).collect();
+                surface.stroke_polyline(&points, line_thickness as i32, color(c));
+            }
+            FrameCommand::Text {
+                x,
+                y,
+                w,
+                h,
+                font: id,
+                background,
+                foreground,
+                ref text,
+                ..
+            } => {
+                if let Some(f) = font(id) {
+                    surface.draw_text(x as i32, y as i32, w as i32, h as i32, text, f, color(background), color(foreground));
+                }
+            }
+            FrameCommand::Image { x, y, w, h, image: recorded, color: c } => {
+                if let Some(img) = image(recorded) {
+                    surface.draw_image(x as i32, y as i32, w as i32, h as i32, img, color(c));
+                }
+            }
+            FrameCommand::Custom { .. } => {}
+        }
+    }
+}
+
+impl Frame {
+    /// Copies every command queued in `ctx`. The context is left untouched.
+    pub fn capture<F: Fn(&UserFont) -> u32>(ctx: &mut Context, font_id: F) -> Frame {
+        Frame {
+            commands: ctx.command_iterator().filter_map(|cmd| FrameCommand::from_command(cmd, &font_id)).collect(),
+        }
+    }
+
+    /// Draws every command; see `FrameCommand::draw` for how fonts, images and custom commands are resolved.
+    pub fn replay<'a, S: Surface + ?Sized, F: Fn(u32) -> Option<&'a UserFont>, I: Fn(u64) -> Option<Image>>(&self, surface: &mut S, font: F, image: I) {
+        for cmd in &self.commands {
+            cmd.draw(surface, &font, &image);
+        }
+    }
+
+    pub fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        out.write_all(&MAGIC)?;
+        write_u16(out, VERSION)?;
+        write_u32(out, self.commands.len() as u32)?;
+
+        for cmd in &self.commands {
+            match *cmd {
+                FrameCommand::Scissor { x, y, w, h } => {
+                    write_u8(out, 1)?;
+                    write_rect(out, x, y, w, h)?;
+                }
+                FrameCommand::Line { begin, end, line_thickness, color } => {
+                    write_u8(out, 2)?;
+                    write_point(out, begin)?;
+                    write_point(out, end)?;
+                    write_u16(out, line_thickness)?;
+                    out.write_all(&color)?;
+                }
+                FrameCommand::Curve { begin, ctrl, end, line_thickness, color } => {
+                    write_u8(out, 3)?;
+                    write_point(out, begin)?;
+                    write_point(out, ctrl[0])?;
+                    write_point(out, ctrl[1])?;
+                    write_point(out, end)?;
+                    write_u16(out, line_thickness)?;
+                    out.write_all(&color)?;
+                }
+                FrameCommand::Rect { x, y, w, h, rounding, line_thickness, color } => {
+                    write_u8(out, 4)?;
+                    write_rect(out, x, y, w, h)?;
+                    write_u16(out, rounding)?;
+                    write_u16(out, line_thickness)?;
+                    out.write_all(&color)?;
+                }
+                FrameCommand::RectFilled { x, y, w, h, rounding, color } => {
+                    write_u8(out, 5)?;
+                    write_rect(out, x, y, w, h)?;
+                    write_u16(out, rounding)?;
+                    out.write_all(&color)?;
+                }
+                FrameCommand::RectMultiColor { x, y, w, h, left, top, right, bottom } => {
+                    write_u8(out, 17)?;
+                    write_rect(out, x, y, w, h)?;
+                    out.write_all(&left)?;
+                    out.write_all(&top)?;
+                    out.write_all(&right)?;
+                    out.write_all(&bottom)?;
+                }
+                FrameCommand::Circle { x, y, w, h, line_thickness, color } => {
+                    write_u8(out, 6)?;
+                    write_rect(out, x, y, w, h)?;
+                    write_u16(out, line_thickness)?;
+                    out.write_all(&color)?;
+                }
+                FrameCommand::CircleFilled { x, y, w, h, color } => {
+                    write_u8(out, 7)?;
+                    write_rect(out, x, y, w, h)?;
+                    out.write_all(&color)?;
+                }
+                FrameCommand::Arc { cx, cy, r, a, line_thickness, color } => {
+                    write_u8(out, 8)?;
+                    write_point(out, (cx, cy))?;
+                    write_u16(out, r)?;
+                    write_f32(out, a[0])?;
+                    write_f32(out, a[1])?;
+                    write_u16(out, line_thickness)?;
+                    out.write_all(&color)?;
+                }
+                FrameCommand::ArcFilled { cx, cy, r, a, color } => {
+                    write_u8(out, 9)?;
+                    write_point(out, (cx, cy))?;
+                    write_u16(out, r)?;
+                    write_f32(out, a[0])?;
+                    write_f32(out, a[1])?;
+                    out.write_all(&color)?;
+                }
+                FrameCommand::Triangle { a, b, c, line_thickness, color } => {
+                    write_u8(out, 10)?;
+                    write_point(out, a)?;
+                    write_point(out, b)?;
+                    write_point(out, c)?;
+                    write_u16(out, line_thickness)?;
+                    out.write_all(&color)?;
+                }
+                FrameCommand::TriangleFilled { a, b, c, color } => {
+                    write_u8(out, 11)?;
+                    write_point(out, a)?;
+                    write_point(out, b)?;
+                    write_point(out, c)?;
+                    out.write_all(&color)?;
+                }
+                FrameCommand::Polygon { ref points, line_thickness, color } => {
+                    write_u8(out, 12)?;
+                    write_points(out, points)?;
+                    write_u16(out, line_thickness)?;
+                    out.write_all(&color)?;
+                }
+                FrameCommand::PolygonFilled { ref points, color } => {
+                    write_u8(out, 13)?;
+                    write_points(out, points)?;
+                    out.write_all(&color)?;
+                }
+                FrameCommand::Polyline { ref points, line_thickness, color } => {
+                    write_u8(out, 14)?;
+                    write_points(out, points)?;
+                    write_u16(out, line_thickness)?;
+                    out.write_all(&color)?;
+                }
+                FrameCommand::Text {
+                    x,
+                    y,
+                    w,
+                    h,
+                    font,
+                    height,
+                    background,
+                    foreground,
+                    ref text,
+                } => {
+                    write_u8(out, 15)?;
+                    write_rect(out, x, y, w, h)?;
+                    write_u32(out, font)?;
+                    write_f32(out, height)?;
+                    out.write_all(&background)?;
+                    out.write_all(&foreground)?;
+                    write_u32(out, text.len() as u32)?;
+                    out.write_all(text)?;
+                }
+                FrameCommand::Image { x, y, w, h, image, color } => {
+                    write_u8(out, 16)?;
+                    write_rect(out, x, y, w, h)?;
+                    write_u64(out, image)?;
+                    out.write_all(&color)?;
+                }
+                FrameCommand::Custom { x, y, w, h, data } => {
+                    write_u8(out, 18)?;
+                    write_rect(out, x, y, w, h)?;
+                    write_u64(out, data)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn read<R: Read>(input: &mut R) -> io::Result<Frame> {
+        let mut magic = [0u8; 4];
+        input.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a nuklear frame recording"));
+        }
+        let version = read_u16(input)?;
+        if version != VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("unsupported frame recording version {}", version)));
+        }
+
+        // counts come from the file, so nothing is reserved up front: a corrupt one fails at the end of the input instead
+        let count = read_u32(input)?;
+        let mut commands = Vec::new();
+        for _ in 0..count {
+            let cmd = match read_u8(input)? {
+                1 => {
+                    let (x, y, w, h) = read_rect(input)?;
+                    FrameCommand::Scissor { x: x, y: y, w: w, h: h }
+                }
+                2 => FrameCommand::Line {
+                    begin: read_point(input)?,
+                    end: read_point(input)?,
+                    line_thickness: read_u16(input)?,
+                    color: read_rgba(input)?,
+                },
+                3 => FrameCommand::Curve {
+                    begin: read_point(input)?,
+                    ctrl: [read_point(input)?, read_point(input)?],
+                    end: read_point(input)?,
+                    line_thickness: read_u16(input)?,
+                    color: read_rgba(input)?,
+                },
+                4 => {
+                    let (x, y, w, h) = read_rect(input)?;
+                    FrameCommand::Rect {
+                        x: x,
+                        y: y,
+                        w: w,
+                        h: h,
+                        rounding: read_u16(input)?,
+                        line_thickness: read_u16(input)?,
+                        color: read_rgba(input)?,
+                    }
+                }
+                5 => {
+                    let (x, y, w, h) = read_rect(input)?;
+                    FrameCommand::RectFilled {
+                        x: x,
+                        y: y,
+                        w: w,
+                        h: h,
+                        rounding: read_u16(input)?,
+                        color: read_rgba(input)?,
+                    }
+                }
+                6 => {
+                    let (x, y, w, h) = read_rect(input)?;
+                    FrameCommand::Circle {
+                        x: x,
+                        y: y,
+                        w: w,
+                        h: h,
+                        line_thickness: read_u16(input)?,
+                        color: read_rgba(input)?,
+                    }
+                }
+                7 => {
+                    let (x, y, w, h) = read_rect(input)?;
+                    FrameCommand::CircleFilled { x: x, y: y, w: w, h: h, color: read_rgba(input)? }
+                }
+                8 => {
+                    let (cx, cy) = read_point(input)?;
+                    FrameCommand::Arc {
+                        cx: cx,
+                        cy: cy,
+                        r: read_u16(input)?,
+                        a: [read_f32(input)?, read_f32(input)?],
+                        line_thickness: read_u16(input)?,
+                        color: read_rgba(input)?,
+                    }
+                }
+                9 => {
+                    let (cx, cy) = read_point(input)?;
+                    FrameCommand::ArcFilled {
+                        cx: cx,
+                        cy: cy,
+                        r: read_u16(input)?,
+                        a: [read_f32(input)?, read_f32(input)?],
+                        color: read_rgba(input)?,
+                    }
+                }
+                10 => FrameCommand::Triangle {
+                    a: read_point(input)?,
+                    b: read_point(input)?,
+                    c: read_point(input)?,
+                    line_thickness: read_u16(input)?,
+                    color: read_rgba(input)?,
+                },
+                11 => FrameCommand::TriangleFilled {
+                    a: read_point(input)?,
+                    b: read_point(input)?,
+                    c: read_point(input)?,
+                    color: read_rgba(input)?,
+                },
+                12 => FrameCommand::Polygon {
+                    points: read_points(input)?,
+                    line_thickness: read_u16(input)?,
+                    color: read_rgba(input)?,
+                },
+                13 => FrameCommand::PolygonFilled {
+                    points: read_points(input)?,
+                    color: read_rgba(input)?,
+                },
+                14 => FrameCommand::Polyline {
+                    points: read_points(input)?,
+                    line_thickness: read_u16(input)?,
+                    color: read_rgba(input)?,
+                },
+                15 => {
+                    let (x, y, w, h) = read_rect(input)?;
+                    let font = read_u32(input)?;
+                    let height = read_f32(input)?;
+                    let background = read_rgba(input)?;
+                    let foreground = read_rgba(input)?;
+                    let len = read_u32(input)?;
+                    let text = read_bytes(input, len)?;
+                    FrameCommand::Text {
+                        x: x,
+                        y: y,
+                        w: w,
+                        h: h,
+                        font: font,
+                        height: height,
+                        background: background,
+                        foreground: foreground,
+                        text: text,
+                    }
+                }
+                16 => {
+                    let (x, y, w, h) = read_rect(input)?;
+                    FrameCommand::Image {
+                        x: x,
+                        y: y,
+                        w: w,
+                        h: h,
+                        image: read_u64(input)?,
+                        color: read_rgba(input)?,
+                    }
+                }
+                17 => {
+                    let (x, y, w, h) = read_rect(input)?;
+                    FrameCommand::RectMultiColor {
+                        x: x,
+                        y: y,
+                        w: w,
+                        h: h,
+                        left: read_rgba(input)?,
+                        top: read_rgba(input)?,
+                        right: read_rgba(input)?,
+                        bottom: read_rgba(input)?,
+                    }
+                }
+                18 => {
+                    let (x, y, w, h) = read_rect(input)?;
+                    FrameCommand::Custom { x: x, y: y, w: w, h: h, data: read_u64(input)? }
+                }
+                tag => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("unknown frame command tag {}", tag))),
+            };
+            commands.push(cmd);
+        }
+
+        Ok(Frame { commands: commands })
+    }
+}
+
+fn write_u8<W: Write>(out: &mut W, v: u8) -> io::Result<()> {
+    out.write_all(&[v])
+}
+
+fn write_u16<W: Write>(out: &mut W, v: u16) -> io::Result<()> {
+    out.write_all(&v.to_le_bytes())
+}
+
+fn write_u32<W: Write>(out: &mut W, v: u32) -> io::Result<()> {
+    out.write_all(&v.to_le_bytes())
+}
+
+fn write_u64<W: Write>(out: &mut W, v: u64) -> io::Result<()> {
+    out.write_all(&v.to_le_bytes())
+}
+
+fn write_f32<W: Write>(out: &mut W, v: f32) -> io::Result<()> {
+    write_u32(out, v.to_bits())
+}
+
+fn write_point<W: Write>(out: &mut W, p: Point) -> io::Result<()> {
+    write_u16(out, p.0 as u16)?;
+    write_u16(out, p.1 as u16)
+}
+
+fn write_points<W: Write>(out: &mut W, points: &[Point]) -> io::Result<()> {
+    write_u32(out, points.len() as u32)?;
+    for p in points {
+        write_point(out, *p)?;
+    }
+    Ok(())
+}
+
+fn write_rect<W: Write>(out: &mut W, x: i16, y: i16, w: u16, h: u16) -> io::Result<()> {
+    write_point(out, (x, y))?;
+    write_u16(out, w)?;
+    write_u16(out, h)
+}
+
+fn read_u8<R: Read>(input: &mut R) -> io::Result<u8> {
+    let mut b = [0u8; 1];
+    input.read_exact(&mut b)?;
+    Ok(b[0])
+}
+
+fn read_u16<R: Read>(input: &mut R) -> io::Result<u16> {
+    let mut b = [0u8; 2];
+    input.read_exact(&mut b)?;
+    Ok(u16::from_le_bytes(b))
+}
+
+fn read_u32<R: Read>(input: &mut R) -> io::Result<u32> {
+    let mut b = [0u8; 4];
+    input.read_exact(&mut b)?;
+    Ok(u32::from_le_bytes(b))
+}
+
+fn read_u64<R: Read>(input: &mut R) -> io::Result<u64> {
+    let mut b = [0u8; 8];
+    input.read_exact(&mut b)?;
+    Ok(u64::from_le_bytes(b))
+}
+
+fn read_f32<R: Read>(input: &mut R) -> io::Result<f32> {
+    Ok(f32::from_bits(read_u32(input)?))
+}
+
+fn read_rgba<R: Read>(input: &mut R) -> io::Result<Rgba> {
+    let mut b = [0u8; 4];
+    input.read_exact(&mut b)?;
+    Ok(b)
+}
+
+fn read_point<R: Read>(input: &mut R) -> io::Result<Point> {
+    Ok((read_u16(input)? as i16, read_u16(input)? as i16))
+}
+
+fn read_points<R: Read>(input: &mut R) -> io::Result<Vec<Point>> {
+    let count = read_u32(input)?;
+    let mut points = Vec::new();
+    for _ in 0..count {
+        points.push(read_point(input)?);
+    }
+    Ok(points)
+}
+
+// Reads `len` bytes, growing the buffer only as data actually arrives.
+fn read_bytes<R: Read>(input: &mut R, len: u32) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    Read::take(&mut *input, len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != len as usize {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "frame recording ends inside a text command"));
+    }
+    Ok(bytes)
+}
+
+fn read_rect<R: Read>(input: &mut R) -> io::Result<(i16, i16, u16, u16)> {
+    let (x, y) = read_point(input)?;
+    Ok((x, y, read_u16(input)?, read_u16(input)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recording::{Primitive, RecordingSurface};
+    use std::os::raw::c_void;
+
+    fn every_command() -> Frame {
+        Frame {
+            commands: vec![
+                FrameCommand::Scissor { x: -1, y: 2, w: 3, h: 4 },
+                FrameCommand::Line { begin: (1, 2), end: (3, 4), line_thickness: 2, color: [1, 2, 3, 4] },
+                FrameCommand::Curve { begin: (1, 2), ctrl: [(3, 4), (5, 6)], end: (7, 8), line_thickness: 3, color: [5, 6, 7, 8] },
+                FrameCommand::Rect { x: 1, y: 2, w: 3, h: 4, rounding: 5, line_thickness: 6, color: [9, 10, 11, 12] },
+                FrameCommand::RectFilled { x: 1, y: 2, w: 3, h: 4, rounding: 5, color: [13, 14, 15, 16] },
+                FrameCommand::RectMultiColor { x: 1, y: 2, w: 3, h: 4, left: [1; 4], top: [2; 4], right: [3; 4], bottom: [4; 4] },
+                FrameCommand::Circle { x: 1, y: 2, w: 3, h: 4, line_thickness: 5, color: [17, 18, 19, 20] },
+                FrameCommand::CircleFilled { x: 1, y: 2, w: 3, h: 4, color: [21, 22, 23, 24] },
+                FrameCommand::Arc { cx: 1, cy: 2, r: 3, a: [0.5, -1.5], line_thickness: 4, color: [25, 26, 27, 28] },
+                FrameCommand::ArcFilled { cx: 1, cy: 2, r: 3, a: [0.25, 6.5], color: [29, 30, 31, 32] },
+                FrameCommand::Triangle { a: (1, 2), b: (3, 4), c: (5, 6), line_thickness: 7, color: [33, 34, 35, 36] },
+                FrameCommand::TriangleFilled { a: (1, 2), b: (3, 4), c: (5, 6), color: [37, 38, 39, 40] },
+                FrameCommand::Polygon { points: vec![(1, 2), (3, 4), (5, -6)], line_thickness: 2, color: [41, 42, 43, 44] },
+                FrameCommand::PolygonFilled { points: vec![(1, 2), (3, 4), (5, 6), (7, 8)], color: [45, 46, 47, 48] },
+                FrameCommand::Polyline { points: vec![(1, 2), (3, 4)], line_thickness: 3, color: [49, 50, 51, 52] },
+                FrameCommand::Text {
+                    x: 1,
+                    y: 2,
+                    w: 3,
+                    h: 4,
+                    font: 7,
+                    height: 13.0,
+                    background: [0, 0, 0, 0],
+                    foreground: [255; 4],
+                    text: "héllo".as_bytes().to_vec(),
+                },
+                FrameCommand::Image { x: 1, y: 2, w: 3, h: 4, image: 0xdead_beef, color: [53, 54, 55, 56] },
+                FrameCommand::Custom { x: 1, y: 2, w: 3, h: 4, data: 42 },
+            ],
+        }
+    }
+
+    fn written(frame: &Frame) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        frame.write(&mut bytes).unwrap();
+        bytes
+    }
+
+    fn invalid_data(bytes: &[u8]) -> bool {
+        Frame::read(&mut &bytes[..]).err().map(|e| e.kind()) == Some(io::ErrorKind::InvalidData)
+    }
+
+    #[test]
+    fn every_command_survives_a_round_trip() {
+        let frame = every_command();
+        assert_eq!(Frame::read(&mut &written(&frame)[..]).unwrap(), frame);
+    }
+
+    #[test]
+    fn truncated_recordings_are_errors() {
+        let bytes = written(&every_command());
+        for len in 0..bytes.len() {
+            assert!(Frame::read(&mut &bytes[..len]).is_err(), "read {} of {} bytes", len, bytes.len());
+        }
+    }
+
+    #[test]
+    fn wrong_magic_and_version_are_rejected() {
+        let bytes = written(&every_command());
+
+        let mut magic = bytes.clone();
+        magic[0] = b'X';
+        assert!(invalid_data(&magic));
+
+        for &version in &[0, VERSION + 1] {
+            let mut other = bytes.clone();
+            other[4..6].copy_from_slice(&version.to_le_bytes());
+            assert!(invalid_data(&other), "version {}", version);
+        }
+    }
+
+    #[test]
+    fn unknown_tags_are_rejected() {
+        let mut bytes = written(&Frame::default());
+        bytes[6..10].copy_from_slice(&1u32.to_le_bytes());
+        bytes.push(0xff);
+        assert!(invalid_data(&bytes));
+    }
+
+    #[test]
+    fn huge_counts_fail_at_the_end_of_the_input() {
+        let mut bytes = written(&Frame::default());
+        bytes[6..10].copy_from_slice(&u32::max_value().to_le_bytes());
+        assert!(Frame::read(&mut &bytes[..]).is_err());
+
+        let text = FrameCommand::Text { x: 0, y: 0, w: 0, h: 0, font: 0, height: 0.0, background: [0; 4], foreground: [0; 4], text: Vec::new() };
+        let mut bytes = written(&Frame { commands: vec![text] });
+        let len = bytes.len();
+        bytes[len - 4..].copy_from_slice(&u32::max_value().to_le_bytes());
+        assert!(Frame::read(&mut &bytes[..]).is_err());
+    }
+    #[test]
+    fn replay_draws_mapped_images_only_and_never_custom_commands() {
+        let frame = Frame {
+            commands: vec![
+                FrameCommand::Image { x: 1, y: 2, w: 3, h: 4, image: 7, color: [255; 4] },
+                FrameCommand::Image { x: 5, y: 6, w: 7, h: 8, image: 8, color: [255; 4] },
+                FrameCommand::Custom { x: 0, y: 0, w: 9, h: 9, data: 0x1234 },
+                FrameCommand::Line { begin: (0, 0), end: (1, 1), line_thickness: 1, color: [255; 4] },
+            ],
+        };
+        let mut surface = RecordingSurface::new();
+        frame.replay(&mut surface, |_| None, |recorded| if recorded == 7 { Some(Image::with_ptr(0x70 as *mut c_void)) } else { None });
+
+        let primitives: Vec<Primitive> = surface.take_records().into_iter().map(|r| r.primitive).collect();
+        assert_eq!(primitives, vec![Primitive::Image { x: 1, y: 2, w: 3, h: 4, handle: 0x70 }, Primitive::Line { x0: 0, y0: 0, x1: 1, y1: 1 }]);
+    }
+}
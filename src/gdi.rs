@@ -0,0 +1,2265 @@
+use winapi::ctypes;
+use winapi::ctypes::c_void;
+use winapi::shared::basetsd;
+use winapi::shared::minwindef;
+use winapi::shared::ntdef;
+use winapi::shared::windef;
+use winapi::um::errhandlingapi;
+use winapi::um::libloaderapi;
+use winapi::um::stringapiset;
+use winapi::um::winbase;
+use winapi::um::wingdi;
+use winapi::um::winnls;
+use winapi::um::winuser;
+
+mod font;
+#[cfg(feature = "own_window")]
+mod own_window;
+mod vertex;
+
+pub use self::font::{FontDescriptor, FontError, FontQuality};
+pub use crate::surface::{LineCap, LineJoin};
+
+#[cfg(any(test, feature = "conformance"))]
+use crate::conformance::Readback;
+use crate::dump;
+use crate::frame::Frame;
+use crate::supersample::{self, Quality, Scaled};
+use crate::surface::{self, convert_color, FillRule, Surface};
+use nuklear::nuklear_sys as nksys;
+use nuklear::*;
+use std::collections::HashMap;
+use std::path::Path;
+use std::{ffi, mem, ptr, slice, str};
+
+/// Identifies a font of a `Drawer`. The id of a removed font stays invalid, even after its slot is reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GdiFontID {
+    index: u16,
+    generation: u16,
+}
+
+impl GdiFontID {
+    /// Packs the id into the `u32` that recorded frames store.
+    pub fn to_raw(self) -> u32 {
+        (self.generation as u32) << 16 | self.index as u32
+    }
+
+    pub fn from_raw(raw: u32) -> GdiFontID {
+        GdiFontID {
+            index: raw as u16,
+            generation: (raw >> 16) as u16,
+        }
+    }
+}
+
+struct FontSlot<T> {
+    font: Option<T>,
+    generation: u16,
+}
+
+// Fonts are boxed so the `nk_user_font` handed to nuklear keeps its address however many fonts are added.
+// Removing a font frees its slot for reuse under the next generation; a slot whose generations run out is retired instead,
+// so that no id can ever match a later font.
+struct FontStore<T = Box<GdiFont>> {
+    slots: Vec<FontSlot<T>>,
+}
+
+impl<T> Default for FontStore<T> {
+    fn default() -> FontStore<T> {
+        FontStore { slots: Vec::new() }
+    }
+}
+
+impl<T> FontStore<T> {
+    fn insert(&mut self, font: T) -> GdiFontID {
+        match self.slots.iter().position(|s| s.font.is_none() && s.generation != u16::max_value()) {
+            Some(index) => {
+                let slot = &mut self.slots[index];
+                slot.font = Some(font);
+                GdiFontID { index: index as u16, generation: slot.generation }
+            }
+            None => {
+                assert!(self.slots.len() <= u16::max_value() as usize, "too many fonts");
+                self.slots.push(FontSlot { font: Some(font), generation: 0 });
+                GdiFontID { index: (self.slots.len() - 1) as u16, generation: 0 }
+            }
+        }
+    }
+
+    fn get(&self, id: GdiFontID) -> Option<&T> {
+        match self.slots.get(id.index as usize) {
+            Some(slot) if slot.generation == id.generation => slot.font.as_ref(),
+            _ => None,
+        }
+    }
+
+    fn remove(&mut self, id: GdiFontID) -> Option<T> {
+        match self.slots.get_mut(id.index as usize) {
+            Some(slot) if slot.generation == id.generation && slot.font.is_some() => {
+                // an occupied slot is never at the last generation, see `insert`
+                slot.generation += 1;
+                slot.font.take()
+            }
+            _ => None,
+        }
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (GdiFontID, &T)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| slot.font.as_ref().map(|f| (GdiFontID { index: index as u16, generation: slot.generation }, f)))
+    }
+}
+
+struct GdiFont {
+    nk: nksys::nk_user_font,
+    height: i32,
+    ascent: i32,
+    handle: windef::HFONT,
+    dc: windef::HDC,
+    atlas: vertex::GlyphAtlas,
+}
+
+impl GdiFont {
+    pub unsafe fn new(desc: &FontDescriptor) -> Result<GdiFont, FontError> {
+        let mut metric = wingdi::TEXTMETRICW {
+            tmHeight: 0,
+            tmAscent: 0,
+            tmDescent: 0,
+            tmInternalLeading: 0,
+            tmExternalLeading: 0,
+            tmAveCharWidth: 0,
+            tmMaxCharWidth: 0,
+            tmWeight: 0,
+            tmOverhang: 0,
+            tmDigitizedAspectX: 0,
+            tmDigitizedAspectY: 0,
+            tmFirstChar: 0,
+            tmLastChar: 0,
+            tmDefaultChar: 0,
+            tmBreakChar: 0,
+            tmItalic: 0,
+            tmUnderlined: 0,
+            tmStruckOut: 0,
+            tmPitchAndFamily: 0,
+            tmCharSet: 0,
+        };
+        let handle = desc.create()?;
+        let dc = wingdi::CreateCompatibleDC(ptr::null_mut());
+
+        let previous = wingdi::SelectObject(dc, handle as *mut c_void);
+        if let Err(e) = desc.verify(dc) {
+            wingdi::SelectObject(dc, previous);
+            wingdi::DeleteObject(handle as *mut c_void);
+            wingdi::DeleteDC(dc);
+            return Err(e);
+        }
+        wingdi::GetTextMetricsW(dc, &mut metric);
+
+        Ok(GdiFont {
+            nk: mem::uninitialized(),
+            height: metric.tmHeight,
+            ascent: metric.tmAscent,
+            handle: handle as windef::HFONT,
+            dc: dc,
+            atlas: vertex::GlyphAtlas::new(),
+        })
+    }
+}
+
+impl Drop for GdiFont {
+    fn drop(&mut self) {
+        unsafe {
+            // the font is selected into its DC, which has to go first
+            wingdi::DeleteDC(self.dc);
+            wingdi::DeleteObject(self.handle as *mut c_void);
+        }
+    }
+}
+
+/// How `Drawer::render` rasterizes a frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RenderMode {
+    /// Every nuklear command becomes GDI calls.
+    Commands,
+    /// The context goes through `nk_convert` and the anti-aliased triangles are filled in software, with nuklear's own
+    /// alpha and edge smoothing. Text uses glyph atlases built from the GDI fonts; `Quality` does not apply.
+    /// `nk_convert` drops custom commands, so custom draw callbacks are not called in this mode.
+    Vertices,
+}
+
+pub struct Drawer {
+    bitmap: windef::HBITMAP,
+    window_dc: windef::HDC,
+    memory_dc: windef::HDC,
+    width: i32,
+    height: i32,
+    // declared before `fonts` so it is dropped first, deselecting them from every DC it draws to
+    surface: GdiSurface,
+    fonts: FontStore,
+    // as requested; supersampling is only active while `supersampled` holds a buffer
+    quality: Quality,
+    // the enlarged target while supersampling
+    supersampled: Option<Dib>,
+    vertex: Option<vertex::VertexRenderer>,
+    // declared after `fonts` and `surface` so it is dropped after every font using it is deleted
+    private_fonts: font::PrivateFonts,
+
+    window: Option<windef::HWND>,
+}
+
+impl Drawer {
+    pub fn new(window_dc: windef::HDC, width: u16, height: u16, window: Option<windef::HWND>) -> Drawer {
+        unsafe {
+            let memory_dc = wingdi::CreateCompatibleDC(window_dc);
+            let drawer = Drawer {
+                bitmap: wingdi::CreateCompatibleBitmap(window_dc, width as i32, height as i32),
+                window_dc: window_dc,
+                memory_dc: memory_dc,
+                width: width as i32,
+                height: height as i32,
+                fonts: FontStore::default(),
+                surface: GdiSurface::new(memory_dc, width as i32, height as i32),
+                quality: Quality::Fast,
+                supersampled: None,
+                vertex: None,
+                private_fonts: font::PrivateFonts::default(),
+
+                window: window,
+            };
+            wingdi::SelectObject(drawer.memory_dc, drawer.bitmap as *mut c_void);
+
+            drawer
+        }
+    }
+
+    pub fn install_statics(&self, context: &mut Context) {
+        unsafe {
+            let context: &mut nksys::nk_context = &mut *(context as *mut _ as *mut nksys::nk_context);
+            context.clip.copy = Some(nk_gdi_clipbard_copy);
+            context.clip.paste = Some(nk_gdi_clipbard_paste);
+        }
+    }
+
+    pub fn window(&self) -> Option<windef::HWND> {
+        self.window
+    }
+
+    pub fn process_events(&mut self, ctx: &mut Context) -> bool {
+        unsafe {
+            let mut msg: winuser::MSG = mem::zeroed();
+            ctx.input_begin();
+
+            if winuser::GetMessageW(&mut msg, ptr::null_mut(), 0, 0) <= 0 {
+                return false;
+            } else {
+                winuser::TranslateMessage(&msg);
+                winuser::DispatchMessageW(&msg);
+            }
+
+            #[cfg(feature = "own_window")]
+            own_window::process_events(self, ctx);
+
+            ctx.input_end();
+        }
+        true
+    }
+
+    /// Creates the font, failing with `FontError::Substituted` when its face is not available.
+    pub fn new_font(&mut self, desc: &FontDescriptor) -> Result<GdiFontID, FontError> {
+        let mut gdifont = Box::new(unsafe { GdiFont::new(desc)? });
+        // the box keeps this address when it moves into the store
+        let userdata = &mut *gdifont as *mut GdiFont as *mut ::std::os::raw::c_void;
+
+        unsafe {
+            ptr::write(
+                &mut gdifont.nk,
+                nksys::nk_user_font {
+                    userdata: nksys::nk_handle_ptr(userdata),
+                    height: gdifont.height as f32,
+                    width: None,
+                    query: None,
+                    texture: nksys::nk_handle::default(),
+                },
+            );
+
+            gdifont.nk.height = gdifont.height as f32;
+            gdifont.nk.width = Some(nk_gdifont_get_text_width);
+            gdifont.nk.query = Some(vertex::nk_gdifont_query_glyph);
+            gdifont.nk.texture = nksys::nk_handle_ptr(userdata);
+        }
+
+        Ok(self.fonts.insert(gdifont))
+    }
+
+    /// Makes the fonts in a font file available to `new_font` under their family names, for this process only.
+    /// They are unregistered when the drawer is dropped.
+    pub fn add_font_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), FontError> {
+        self.private_fonts.add_file(path.as_ref())
+    }
+
+    /// Like `add_font_file`, for font data in memory such as a TTF embedded with `include_bytes!`.
+    pub fn add_font_memory(&mut self, data: &[u8]) -> Result<(), FontError> {
+        self.private_fonts.add_memory(data)
+    }
+
+    /// `None` for an id that was never issued or whose font has been removed.
+    pub fn font_by_id(&self, id: GdiFontID) -> Option<&UserFont> {
+        self.fonts.get(id).map(|f| unsafe { &*(&f.nk as *const _ as *const UserFont) })
+    }
+
+    /// Deletes the font; returns `false` if `id` is stale. Nothing may use the font afterwards: switch the context's
+    /// style to another font and render or clear the pending commands first.
+    pub fn remove_font(&mut self, id: GdiFontID) -> bool {
+        match self.fonts.remove(id) {
+            Some(font) => {
+                unsafe { self.surface.forget_font(font.handle) }
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn font_id_of(&self, font: &UserFont) -> Option<GdiFontID> {
+        let font = font as *const _ as *const nksys::nk_user_font;
+        self.fonts.iter().find(|&(_, f)| &f.nk as *const _ == font).map(|(id, _)| id)
+    }
+
+    pub fn pen_cache_stats(&self) -> PenCacheStats {
+        self.surface.pen_cache_stats()
+    }
+
+    pub fn set_pen_cache_capacity(&mut self, capacity: usize) {
+        self.surface.set_pen_cache_capacity(capacity)
+    }
+
+    /// Confines the UI to part of the window: clearing, every scissor and the copy to the window stay within `clip`,
+    /// which is in window pixels. `None` gives the UI the whole window again.
+    pub fn set_outer_clip(&mut self, clip: Option<windef::RECT>) {
+        self.surface.set_outer_clip(clip);
+    }
+
+    pub fn outer_clip(&self) -> Option<windef::RECT> {
+        self.surface.outer_clip()
+    }
+
+    /// Registers a callback for custom draw commands (`nk_push_custom`). It gets the memory DC, the command and clip rectangles
+    /// and the user handle; the DC state is restored afterwards, so the callback is free to select its own objects.
+    pub fn add_custom_callback<F: FnMut(&CustomDraw) + 'static>(&mut self, callback: F) {
+        self.surface.add_custom_callback(Box::new(callback));
+    }
+
+    pub fn clear_custom_callbacks(&mut self) {
+        self.surface.clear_custom_callbacks();
+    }
+
+    /// Number of GDI state changes (object selections, color, mode and clip updates) issued by the last rendered frame.
+    pub fn state_changes(&self) -> u32 {
+        self.surface.state_changes()
+    }
+
+    /// Deletes every cached pen; the hit/miss counters are kept.
+    pub fn flush_pen_cache(&mut self) {
+        self.surface.flush_pen_cache()
+    }
+
+    /// Join and cap used for strokes thicker than one pixel; mitered joins and square caps by default.
+    pub fn set_stroke_style(&mut self, join: LineJoin, cap: LineCap) {
+        self.surface.set_stroke_style(join, cap)
+    }
+
+    /// Alternate (even-odd) or winding fill for self-intersecting polygons.
+    pub fn set_fill_rule(&mut self, rule: FillRule) {
+        self.surface.set_fill_rule(rule)
+    }
+
+    pub fn fill_rule(&self) -> FillRule {
+        self.surface.fill_rule()
+    }
+
+    /// Whether text too wide for its widget is only clipped (the default) or also ends in "…". Applies to the command path.
+    pub fn set_text_overflow(&mut self, overflow: TextOverflow) {
+        self.surface.set_text_overflow(overflow)
+    }
+
+    pub fn text_overflow(&self) -> TextOverflow {
+        self.surface.text_overflow()
+    }
+
+    /// Draws text over what is already there instead of on its background color; by default only backgrounds with alpha 0 are skipped.
+    pub fn set_text_background(&mut self, background: TextBackground) {
+        self.surface.set_text_background(background)
+    }
+
+    pub fn text_background(&self) -> TextBackground {
+        self.surface.text_background()
+    }
+
+    /// Copies the commands queued in `ctx`, with fonts referenced by their `GdiFontID`.
+    pub fn capture_frame(&self, ctx: &mut Context) -> Frame {
+        Frame::capture(ctx, |font| self.font_id_of(font).map(GdiFontID::to_raw).unwrap_or(u32::max_value()))
+    }
+
+    /// Dumps the commands queued in `ctx` as text; call it before `render`, which clears the context.
+    pub fn dump_commands(&self, ctx: &mut Context) -> String {
+        dump::dump_frame(&self.capture_frame(ctx))
+    }
+
+    /// Renders a recorded frame with this drawer's fonts, as `render` does for a live `Context`.
+    ///
+    /// `images` maps the handle values stored in the frame to images of this process, e.g. through a table saved by the recording side;
+    /// unmapped images are skipped. Custom commands are not replayed, so custom callbacks never see recorded pointers.
+    pub fn render_frame<I: Fn(u64) -> Option<Image>>(&mut self, frame: &Frame, clear: Color, images: I) {
+        self.surface.reset_state_changes();
+        let fonts = &self.fonts;
+        unsafe {
+            nk_gdi_draw(&mut self.surface, self.supersampled.as_ref(), self.quality, self.memory_dc, self.width, self.height, clear, |target| {
+                frame.replay(target, |id| fonts.get(GdiFontID::from_raw(id)).map(|f| &*(&f.nk as *const _ as *const UserFont)), images)
+            });
+            self.blit(self.window_dc);
+        }
+    }
+
+    #[cfg(feature = "piston_image")]
+    pub fn add_image(&mut self, img: &image::DynamicImage) -> Handle {
+        use image::GenericImageView;
+
+        let (w, h) = img.dimensions();
+
+        let hbmp: windef::HBITMAP;
+
+        let bminfo = wingdi::BITMAPINFO {
+            bmiHeader: wingdi::BITMAPINFOHEADER {
+                biSize: mem::size_of::<wingdi::BITMAPINFOHEADER>() as u32,
+                biWidth: w as i32,
+                biHeight: h as i32,
+                biPlanes: 1,
+                biBitCount: 32,
+                biCompression: wingdi::BI_RGB,
+                biSizeImage: 0,
+                biXPelsPerMeter: 0,
+                biYPelsPerMeter: 0,
+                biClrUsed: 0,
+                biClrImportant: 0,
+            },
+            bmiColors: unsafe { mem::zeroed() },
+        };
+
+        unsafe {
+            let mut pv_image_bits = ptr::null_mut();
+            let hdc_screen = winuser::GetDC(ptr::null_mut());
+            hbmp = wingdi::CreateDIBSection(hdc_screen, &bminfo, wingdi::DIB_RGB_COLORS, &mut pv_image_bits, ptr::null_mut(), 0);
+            winuser::ReleaseDC(ptr::null_mut(), hdc_screen);
+            if hbmp.is_null() {
+                panic!("Out of memory!");
+            }
+
+            let mut img = img.flipv().to_rgba();
+            for x in 0..w {
+                for y in 0..h {
+                    let p = img.get_pixel_mut(x, y);
+                    let r = p[0];
+                    let b = p[2];
+                    p[0] = b;
+                    p[2] = r;
+                }
+            }
+            ptr::copy(img.into_raw().as_ptr(), pv_image_bits as *mut u8, (w * h * 4) as usize);
+            /*{
+                // couldn't extract image; delete HBITMAP
+                wingdi::DeleteObject(hbmp as *mut c_void);
+                hbmp = ptr::null_mut();
+                return;
+            }*/
+            //TODO
+            Handle::from_ptr(hbmp as *mut ::std::os::raw::c_void)
+        }
+    }
+
+    pub fn handle_event(&mut self, ctx: &mut Context, wnd: windef::HWND, msg: minwindef::UINT, wparam: minwindef::WPARAM, lparam: minwindef::LPARAM) -> bool {
+        match msg {
+            winuser::WM_SIZE => {
+                let width = lparam as u16;
+                let height = (lparam >> 16) as u16;
+                // minimizing reports 0x0; the buffers are kept for when the window comes back
+                if width > 0 && height > 0 && (width as i32 != self.width || height as i32 != self.height) {
+                    unsafe {
+                        wingdi::DeleteObject(self.bitmap as *mut c_void);
+                        self.bitmap = wingdi::CreateCompatibleBitmap(self.window_dc, width as i32, height as i32);
+                        self.width = width as i32;
+                        self.height = height as i32;
+                        wingdi::SelectObject(self.memory_dc, self.bitmap as *mut c_void);
+                        self.update_target();
+                    }
+                }
+            }
+            winuser::WM_PAINT => {
+                unsafe {
+                    let mut paint: winuser::PAINTSTRUCT = mem::zeroed();
+                    let dc = winuser::BeginPaint(wnd, &mut paint);
+                    self.blit(dc);
+                    winuser::EndPaint(wnd, &paint);
+                }
+                return true;
+            }
+            winuser::WM_KEYDOWN | winuser::WM_KEYUP | winuser::WM_SYSKEYDOWN | winuser::WM_SYSKEYUP => {
+                let down = ((lparam >> 31) & 1) == 0;
+                let ctrl = unsafe { (winuser::GetKeyState(winuser::VK_CONTROL) & (1 << 15)) != 0 };
+
+                match wparam as i32 {
+                    winuser::VK_SHIFT | winuser::VK_LSHIFT | winuser::VK_RSHIFT => {
+                        ctx.input_key(Key::Shift, down);
+                        return true;
+                    }
+                    winuser::VK_DELETE => {
+                        ctx.input_key(Key::Del, down);
+                        return true;
+                    }
+                    winuser::VK_RETURN => {
+                        ctx.input_key(Key::Enter, down);
+                        return true;
+                    }
+                    winuser::VK_TAB => {
+                        ctx.input_key(Key::Tab, down);
+                        return true;
+                    }
+                    winuser::VK_LEFT => {
+                        if ctrl {
+                            ctx.input_key(Key::TextWordLeft, down);
+                        } else {
+                            ctx.input_key(Key::Left, down);
+                        }
+                        return true;
+                    }
+                    winuser::VK_RIGHT => {
+                        if ctrl {
+                            ctx.input_key(Key::TextWordRight, down);
+                        } else {
+                            ctx.input_key(Key::Right, down);
+                        }
+                        return true;
+                    }
+                    winuser::VK_BACK => {
+                        ctx.input_key(Key::Backspace, down);
+                        return true;
+                    }
+                    winuser::VK_HOME => {
+                        ctx.input_key(Key::TextStart, down);
+                        ctx.input_key(Key::ScrollStart, down);
+                        return true;
+                    }
+                    winuser::VK_END => {
+                        ctx.input_key(Key::TextEnd, down);
+                        ctx.input_key(Key::ScrollEnd, down);
+                        return true;
+                    }
+                    winuser::VK_NEXT => {
+                        ctx.input_key(Key::ScrollDown, down);
+                        return true;
+                    }
+                    winuser::VK_PRIOR => {
+                        ctx.input_key(Key::ScrollUp, down);
+                        return true;
+                    }
+                    _ => {}
+                }
+                match wparam as u8 as char {
+                    'C' => {
+                        if ctrl {
+                            ctx.input_key(Key::Copy, down);
+                            return true;
+                        }
+                    }
+                    'V' => {
+                        if ctrl {
+                            ctx.input_key(Key::Paste, down);
+                            return true;
+                        }
+                    }
+                    'X' => {
+                        if ctrl {
+                            ctx.input_key(Key::Cut, down);
+                            return true;
+                        }
+                    }
+                    'Z' => {
+                        if ctrl {
+                            ctx.input_key(Key::TextUndo, down);
+                            return true;
+                        }
+                    }
+                    'R' => {
+                        if ctrl {
+                            ctx.input_key(Key::TextRedo, down);
+                            return true;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            winuser::WM_CHAR => {
+                if wparam >= 32 {
+                    unsafe {
+                        ctx.input_unicode(::std::char::from_u32_unchecked(wparam as u32));
+                    }
+                    return true;
+                }
+            }
+            winuser::WM_LBUTTONDOWN => {
+                ctx.input_button(Button::Left, lparam as u16 as i32, (lparam >> 16) as u16 as i32, true);
+                unsafe {
+                    winuser::SetCapture(wnd);
+                }
+                return true;
+            }
+            winuser::WM_LBUTTONUP => {
+                ctx.input_button(Button::Left, lparam as u16 as i32, (lparam >> 16) as u16 as i32, false);
+                unsafe {
+                    winuser::ReleaseCapture();
+                }
+                return true;
+            }
+            winuser::WM_RBUTTONDOWN => {
+                ctx.input_button(Button::Right, lparam as u16 as i32, (lparam >> 16) as u16 as i32, true);
+                unsafe {
+                    winuser::SetCapture(wnd);
+                }
+                return true;
+            }
+            winuser::WM_RBUTTONUP => {
+                ctx.input_button(Button::Right, lparam as u16 as i32, (lparam >> 16) as u16 as i32, false);
+                unsafe {
+                    winuser::ReleaseCapture();
+                }
+                return true;
+            }
+            winuser::WM_MBUTTONDOWN => {
+                ctx.input_button(Button::Middle, lparam as u16 as i32, (lparam >> 16) as u16 as i32, true);
+                unsafe {
+                    winuser::SetCapture(wnd);
+                }
+                return true;
+            }
+            winuser::WM_MBUTTONUP => {
+                ctx.input_button(Button::Middle, lparam as u16 as i32, (lparam >> 16) as u16 as i32, false);
+                unsafe {
+                    winuser::ReleaseCapture();
+                }
+                return true;
+            }
+            winuser::WM_MOUSEWHEEL => {
+                ctx.input_scroll(Vec2 {
+                    x: 0.0,
+                    y: ((wparam >> 16) as u16) as f32 / winuser::WHEEL_DELTA as f32,
+                });
+                return true;
+            }
+            winuser::WM_MOUSEMOVE => {
+                ctx.input_motion(lparam as u16 as i32, (lparam >> 16) as u16 as i32);
+                return true;
+            }
+            _ => {}
+        }
+        false
+    }
+
+    pub fn render(&mut self, ctx: &mut Context, clear: Color) {
+        self.surface.reset_state_changes();
+        match self.vertex {
+            Some(ref mut vertex) => unsafe {
+                // the backbuffer may still carry the clip of a command-path frame
+                self.surface.reset_clip();
+                vertex.convert(ctx);
+                vertex.draw(ctx, &self.fonts, self.memory_dc, self.width, self.height, clear);
+                self.blit(self.window_dc);
+            },
+            None => self.draw(clear, |target| surface::draw_commands(target, ctx)),
+        }
+        ctx.clear();
+    }
+
+    /// Chooses how `render` turns the context into pixels. Recorded frames (`render_frame`) always go through the command path.
+    pub fn set_render_mode(&mut self, mode: RenderMode) {
+        match mode {
+            RenderMode::Commands => self.vertex = None,
+            RenderMode::Vertices => {
+                if self.vertex.is_none() {
+                    self.vertex = Some(vertex::VertexRenderer::new());
+                }
+            }
+        }
+    }
+
+    pub fn render_mode(&self) -> RenderMode {
+        if self.vertex.is_some() {
+            RenderMode::Vertices
+        } else {
+            RenderMode::Commands
+        }
+    }
+
+    /// Makes the vertex path read image bitmaps again; call it after changing the pixels of an image in use.
+    pub fn clear_image_cache(&mut self) {
+        if let Some(ref mut vertex) = self.vertex {
+            vertex.clear_image_cache();
+        }
+    }
+
+    /// Picks between drawing straight into the backbuffer and supersampling; takes effect from the next frame.
+    /// While the enlarged buffer cannot be allocated the drawer renders as `Quality::Fast`, and tries again on every resize;
+    /// `active_quality` tells which mode is in use.
+    pub fn set_quality(&mut self, quality: Quality) {
+        if quality != self.quality {
+            self.quality = quality;
+            unsafe { self.update_target() }
+        }
+    }
+
+    /// The quality last passed to `set_quality`.
+    pub fn quality(&self) -> Quality {
+        self.quality
+    }
+
+    /// The quality frames are currently rendered with.
+    pub fn active_quality(&self) -> Quality {
+        match self.supersampled {
+            Some(_) => self.quality,
+            None => Quality::Fast,
+        }
+    }
+
+    // Points the surface at the backbuffer or at a supersampled DIB of the current size.
+    unsafe fn update_target(&mut self) {
+        if self.width <= 0 || self.height <= 0 {
+            return;
+        }
+
+        let factor = self.quality.factor();
+        let dib = if factor == 1 { None } else { Dib::new(self.width * factor, self.height * factor) };
+        match dib {
+            Some(dib) => {
+                self.surface.retarget(dib.dc, dib.width, dib.height);
+                self.supersampled = Some(dib);
+                self.surface.set_scale(factor);
+            }
+            None => {
+                self.surface.retarget(self.memory_dc, self.width, self.height);
+                self.supersampled = None;
+                self.surface.set_scale(1);
+            }
+        }
+    }
+
+    fn draw<F: FnOnce(&mut dyn Surface)>(&mut self, clear: Color, draw: F) {
+        unsafe {
+            nk_gdi_draw(&mut self.surface, self.supersampled.as_ref(), self.quality, self.memory_dc, self.width, self.height, clear, draw);
+            self.blit(self.window_dc);
+        }
+    }
+
+    // Copies the backbuffer to `dc`, only the outer clip when there is one so the rest of the window is left to the host.
+    unsafe fn blit(&self, dc: windef::HDC) {
+        let (left, top, right, bottom) = self.surface.outer_clip.unwrap_or((0, 0, self.width, self.height));
+        wingdi::BitBlt(dc, left, top, right - left, bottom - top, self.memory_dc, left, top, wingdi::SRCCOPY);
+    }
+}
+
+impl Drop for Drawer {
+    fn drop(&mut self) {
+        unsafe {
+            self.surface.flush_pen_cache();
+            wingdi::DeleteObject(self.memory_dc as *mut c_void);
+            wingdi::DeleteObject(self.bitmap as *mut c_void);
+        }
+    }
+}
+
+// Clears the target and runs `draw` on it, downsampling into `memory_dc` when drawing went to the supersampled DIB.
+// Takes the drawer's fields one by one so its fonts can stay borrowed while drawing.
+unsafe fn nk_gdi_draw<F: FnOnce(&mut dyn Surface)>(surface: &mut GdiSurface, supersampled: Option<&Dib>, quality: Quality, memory_dc: windef::HDC, width: i32, height: i32, clear: Color, draw: F) {
+    match (quality, supersampled) {
+        (Quality::Supersampled { filter, .. }, Some(dib)) => {
+            let factor = quality.factor();
+            let mut scaled = Scaled::new(surface, factor);
+            scaled.clear(clear);
+            draw(&mut scaled);
+
+            let pixels = supersample::downsample(dib.pixels(), width as usize, height as usize, factor, filter);
+            let bminfo = nk_gdi_dib_info(width, height);
+            wingdi::SetDIBitsToDevice(memory_dc, 0, 0, width as u32, height as u32, 0, 0, 0, height as u32, pixels.as_ptr() as *const c_void, &bminfo, wingdi::DIB_RGB_COLORS);
+        }
+        _ => {
+            surface.clear(clear);
+            draw(surface);
+        }
+    }
+}
+
+const DEFAULT_PEN_CACHE_CAPACITY: usize = 64;
+
+// Mirrors what is currently selected into a DC, so that redundant GDI calls can be skipped.
+// `None` means unknown: the next request for that piece of state is always issued.
+#[derive(Default)]
+struct DcState {
+    pen: Option<windef::HGDIOBJ>,
+    brush: Option<windef::HGDIOBJ>,
+    font: Option<windef::HGDIOBJ>,
+    pen_color: Option<windef::COLORREF>,
+    brush_color: Option<windef::COLORREF>,
+    text_color: Option<windef::COLORREF>,
+    bk_color: Option<windef::COLORREF>,
+    bk_mode: Option<i32>,
+    arc_direction: Option<i32>,
+    poly_fill_mode: Option<i32>,
+    clip: Option<Option<(i32, i32, i32, i32)>>,
+    changes: u32,
+}
+
+impl DcState {
+    fn invalidate(&mut self) {
+        *self = DcState { changes: self.changes, ..DcState::default() };
+    }
+
+    unsafe fn select_pen(&mut self, dc: windef::HDC, pen: windef::HGDIOBJ) {
+        if self.pen != Some(pen) {
+            wingdi::SelectObject(dc, pen);
+            self.pen = Some(pen);
+            self.changes += 1;
+        }
+    }
+
+    unsafe fn select_brush(&mut self, dc: windef::HDC, brush: windef::HGDIOBJ) {
+        if self.brush != Some(brush) {
+            wingdi::SelectObject(dc, brush);
+            self.brush = Some(brush);
+            self.changes += 1;
+        }
+    }
+
+    unsafe fn select_font(&mut self, dc: windef::HDC, font: windef::HGDIOBJ) {
+        if self.font != Some(font) {
+            wingdi::SelectObject(dc, font);
+            self.font = Some(font);
+            self.changes += 1;
+        }
+    }
+
+    unsafe fn set_pen_color(&mut self, dc: windef::HDC, color: windef::COLORREF) {
+        if self.pen_color != Some(color) {
+            wingdi::SetDCPenColor(dc, color);
+            self.pen_color = Some(color);
+            self.changes += 1;
+        }
+    }
+
+    unsafe fn set_brush_color(&mut self, dc: windef::HDC, color: windef::COLORREF) {
+        if self.brush_color != Some(color) {
+            wingdi::SetDCBrushColor(dc, color);
+            self.brush_color = Some(color);
+            self.changes += 1;
+        }
+    }
+
+    unsafe fn set_text_color(&mut self, dc: windef::HDC, color: windef::COLORREF) {
+        if self.text_color != Some(color) {
+            wingdi::SetTextColor(dc, color);
+            self.text_color = Some(color);
+            self.changes += 1;
+        }
+    }
+
+    unsafe fn set_bk_color(&mut self, dc: windef::HDC, color: windef::COLORREF) {
+        if self.bk_color != Some(color) {
+            wingdi::SetBkColor(dc, color);
+            self.bk_color = Some(color);
+            self.changes += 1;
+        }
+    }
+
+    unsafe fn set_bk_mode(&mut self, dc: windef::HDC, mode: i32) {
+        if self.bk_mode != Some(mode) {
+            wingdi::SetBkMode(dc, mode);
+            self.bk_mode = Some(mode);
+            self.changes += 1;
+        }
+    }
+
+    unsafe fn set_poly_fill_mode(&mut self, dc: windef::HDC, mode: i32) {
+        if self.poly_fill_mode != Some(mode) {
+            wingdi::SetPolyFillMode(dc, mode);
+            self.poly_fill_mode = Some(mode);
+            self.changes += 1;
+        }
+    }
+
+    unsafe fn set_arc_direction(&mut self, dc: windef::HDC, direction: i32) {
+        if self.arc_direction != Some(direction) {
+            wingdi::SetArcDirection(dc, direction);
+            self.arc_direction = Some(direction);
+            self.changes += 1;
+        }
+    }
+
+    // `(left, top, right, bottom)` as passed to `IntersectClipRect`, or `None` for no clipping.
+    unsafe fn set_clip(&mut self, dc: windef::HDC, clip: Option<(i32, i32, i32, i32)>) {
+        if self.clip != Some(clip) {
+            wingdi::SelectClipRgn(dc, ptr::null_mut());
+            if let Some((left, top, right, bottom)) = clip {
+                wingdi::IntersectClipRect(dc, left, top, right, bottom);
+            }
+            self.clip = Some(clip);
+            self.changes += 1;
+        }
+    }
+
+    unsafe fn select_fill(&mut self, dc: windef::HDC, color: windef::COLORREF) {
+        self.select_pen(dc, wingdi::GetStockObject(wingdi::DC_PEN as i32));
+        self.select_brush(dc, wingdi::GetStockObject(wingdi::DC_BRUSH as i32));
+        self.set_pen_color(dc, color);
+        self.set_brush_color(dc, color);
+    }
+}
+
+/// What happens to text wider than its command rectangle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextOverflow {
+    /// Cut off at the rectangle's edge.
+    Clip,
+    /// Shortened to the characters that fit followed by "…", then clipped.
+    Ellipsis,
+}
+
+impl Default for TextOverflow {
+    fn default() -> TextOverflow {
+        TextOverflow::Clip
+    }
+}
+
+/// Whether text gets the background color nuklear sends with it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextBackground {
+    /// Painted behind the glyphs, composited when translucent; a background with alpha 0 is not painted.
+    FromCommand,
+    /// Never painted, so labels show whatever gradient or image lies underneath.
+    Transparent,
+}
+
+impl Default for TextBackground {
+    fn default() -> TextBackground {
+        TextBackground::FromCommand
+    }
+}
+
+fn nk_gdi_pen_style(join: LineJoin, cap: LineCap) -> u32 {
+    let join = match join {
+        LineJoin::Miter => wingdi::PS_JOIN_MITER,
+        LineJoin::Bevel => wingdi::PS_JOIN_BEVEL,
+        LineJoin::Round => wingdi::PS_JOIN_ROUND,
+    };
+    let cap = match cap {
+        LineCap::Flat => wingdi::PS_ENDCAP_FLAT,
+        LineCap::Square => wingdi::PS_ENDCAP_SQUARE,
+        LineCap::Round => wingdi::PS_ENDCAP_ROUND,
+    };
+    wingdi::PS_GEOMETRIC | wingdi::PS_SOLID | join | cap
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PenCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    pub live: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct PenKey {
+    color: windef::COLORREF,
+    width: i32,
+    style: u32,
+}
+
+// Least recently used pens are deleted once `capacity` is exceeded.
+struct PenCache {
+    pens: HashMap<PenKey, (windef::HPEN, u64)>,
+    capacity: usize,
+    tick: u64,
+    stats: PenCacheStats,
+    // style of the pens thick strokes ask for
+    stroke_style: u32,
+}
+
+impl PenCache {
+    fn new(capacity: usize) -> PenCache {
+        PenCache {
+            pens: HashMap::new(),
+            capacity: capacity,
+            tick: 0,
+            stats: PenCacheStats::default(),
+            stroke_style: nk_gdi_pen_style(LineJoin::Miter, LineCap::Square),
+        }
+    }
+
+    unsafe fn get(&mut self, dc: windef::HDC, st: &mut DcState, color: windef::COLORREF, width: i32, style: u32) -> windef::HPEN {
+        let key = PenKey { color: color, width: width, style: style };
+        if let Some(pen) = self.lookup(key) {
+            return pen;
+        }
+
+        self.evict(dc, st, self.capacity.saturating_sub(1));
+        let brush = wingdi::LOGBRUSH { lbStyle: wingdi::BS_SOLID, lbColor: color, lbHatch: 0 };
+        let pen = wingdi::ExtCreatePen(style, width as u32, &brush, 0, ptr::null());
+        self.insert(key, pen);
+        pen
+    }
+
+    // Marks a cached pen as used; the bookkeeping below touches no GDI objects.
+    fn lookup(&mut self, key: PenKey) -> Option<windef::HPEN> {
+        self.tick += 1;
+        match self.pens.get_mut(&key) {
+            Some(entry) => {
+                entry.1 = self.tick;
+                self.stats.hits += 1;
+                Some(entry.0)
+            }
+            None => {
+                self.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    fn insert(&mut self, key: PenKey, pen: windef::HPEN) {
+        self.pens.insert(key, (pen, self.tick));
+    }
+
+    // Removes least recently used pens until at most `keep` are left, oldest first.
+    fn take_oldest(&mut self, keep: usize) -> Vec<windef::HPEN> {
+        let mut evicted = Vec::new();
+        while self.pens.len() > keep {
+            let oldest = *self.pens.iter().min_by_key(|&(_, &(_, used))| used).unwrap().0;
+            evicted.push(self.pens.remove(&oldest).unwrap().0);
+            self.stats.evictions += 1;
+        }
+        evicted
+    }
+
+    // a pen still selected into the DC cannot be deleted
+    unsafe fn release(dc: windef::HDC, st: &mut DcState, pen: windef::HPEN) {
+        if st.pen.map_or(true, |selected| selected == pen as windef::HGDIOBJ) {
+            st.pen = None;
+            st.select_pen(dc, wingdi::GetStockObject(wingdi::DC_PEN as i32));
+        }
+        wingdi::DeleteObject(pen as *mut c_void);
+    }
+
+    unsafe fn evict(&mut self, dc: windef::HDC, st: &mut DcState, keep: usize) {
+        for pen in self.take_oldest(keep) {
+            PenCache::release(dc, st, pen);
+        }
+    }
+
+    unsafe fn flush(&mut self, dc: windef::HDC, st: &mut DcState) {
+        for (_, (pen, _)) in self.pens.drain() {
+            PenCache::release(dc, st, pen);
+        }
+    }
+
+    fn stats(&self) -> PenCacheStats {
+        PenCacheStats { live: self.pens.len(), ..self.stats }
+    }
+}
+
+// How `blend` turns the scratch pixels into premultiplied BGRA.
+#[derive(Clone, Copy)]
+enum Tint {
+    // the shape was drawn in opaque white; its brightness is the coverage of this color
+    Coverage(Color),
+    // the shape wrote its own alpha channel (gradients)
+    Premultiply,
+}
+
+// Top-down 32-bit DIB section selected into its own memory DC.
+struct Dib {
+    dc: windef::HDC,
+    bitmap: windef::HBITMAP,
+    bits: *mut u8,
+    width: i32,
+    height: i32,
+}
+
+impl Dib {
+    // `None` when GDI cannot allocate the bitmap, which large supersampled windows can run into.
+    unsafe fn new(width: i32, height: i32) -> Option<Dib> {
+        let bminfo = nk_gdi_dib_info(width, height);
+
+        let mut bits = ptr::null_mut();
+        let dc = wingdi::CreateCompatibleDC(ptr::null_mut());
+        let bitmap = wingdi::CreateDIBSection(dc, &bminfo, wingdi::DIB_RGB_COLORS, &mut bits, ptr::null_mut(), 0);
+        if bitmap.is_null() {
+            wingdi::DeleteDC(dc);
+            return None;
+        }
+        wingdi::SelectObject(dc, bitmap as *mut c_void);
+
+        Some(Dib {
+            dc: dc,
+            bitmap: bitmap,
+            bits: bits as *mut u8,
+            width: width,
+            height: height,
+        })
+    }
+
+    unsafe fn pixels(&self) -> &[u8] {
+        wingdi::GdiFlush();
+        slice::from_raw_parts(self.bits, (self.width * self.height * 4) as usize)
+    }
+}
+
+impl Drop for Dib {
+    fn drop(&mut self) {
+        unsafe {
+            wingdi::DeleteDC(self.dc);
+            wingdi::DeleteObject(self.bitmap as *mut c_void);
+        }
+    }
+}
+
+fn nk_gdi_dib_info(width: i32, height: i32) -> wingdi::BITMAPINFO {
+    wingdi::BITMAPINFO {
+        bmiHeader: wingdi::BITMAPINFOHEADER {
+            biSize: mem::size_of::<wingdi::BITMAPINFOHEADER>() as u32,
+            biWidth: width,
+            biHeight: -height,
+            biPlanes: 1,
+            biBitCount: 32,
+            biCompression: wingdi::BI_RGB,
+            biSizeImage: 0,
+            biXPelsPerMeter: 0,
+            biYPelsPerMeter: 0,
+            biClrUsed: 0,
+            biClrImportant: 0,
+        },
+        bmiColors: unsafe { mem::zeroed() },
+    }
+}
+
+// Offscreen DIB that translucent primitives are drawn into before `AlphaBlend`.
+struct Scratch {
+    dib: Dib,
+    state: DcState,
+    pens: PenCache,
+}
+
+impl Scratch {
+    unsafe fn new(width: i32, height: i32) -> Option<Scratch> {
+        Dib::new(width, height).map(|dib| Scratch {
+            dib: dib,
+            state: DcState::default(),
+            pens: PenCache::new(8),
+        })
+    }
+
+    // Clears the top-left `w`x`h` block and maps logical (`x`, `y`) onto its origin.
+    unsafe fn begin(&mut self, x: i32, y: i32, w: i32, h: i32) {
+        wingdi::SetViewportOrgEx(self.dib.dc, 0, 0, ptr::null_mut());
+        wingdi::PatBlt(self.dib.dc, 0, 0, w, h, wingdi::BLACKNESS);
+        wingdi::SetViewportOrgEx(self.dib.dc, -x, -y, ptr::null_mut());
+    }
+
+    unsafe fn finish(&mut self, w: i32, h: i32, tint: Tint) {
+        wingdi::GdiFlush();
+        wingdi::SetViewportOrgEx(self.dib.dc, 0, 0, ptr::null_mut());
+
+        let stride = self.dib.width as usize * 4;
+        for row in 0..h as usize {
+            let line = slice::from_raw_parts_mut(self.dib.bits.add(row * stride), w as usize * 4);
+            for px in line.chunks_mut(4) {
+                let (b, g, r, a) = match tint {
+                    Tint::Coverage(col) => {
+                        let coverage = px[0].max(px[1]).max(px[2]) as u32 * col.a as u32 / 255;
+                        (col.b as u32, col.g as u32, col.r as u32, coverage)
+                    }
+                    Tint::Premultiply => (px[0] as u32, px[1] as u32, px[2] as u32, px[3] as u32),
+                };
+                px[0] = (b * a / 255) as u8;
+                px[1] = (g * a / 255) as u8;
+                px[2] = (r * a / 255) as u8;
+                px[3] = a as u8;
+            }
+        }
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        unsafe { self.pens.flush(self.dib.dc, &mut self.state) }
+    }
+}
+
+/// What a custom draw callback gets for each `nk_push_custom` command.
+pub struct CustomDraw {
+    /// The memory DC the frame is rendered into. Its state is saved before and restored after the callback.
+    pub dc: windef::HDC,
+    pub rect: windef::RECT,
+    /// The clip rectangle in effect, already selected into `dc`: the last scissor within the outer clip, if either is set.
+    pub clip: Option<windef::RECT>,
+    /// The user handle passed to `nk_push_custom`.
+    pub data: Handle,
+    /// How many device pixels one nuklear unit spans; above 1 when the drawer supersamples.
+    pub scale: i32,
+}
+
+pub type CustomDrawCallback = Box<dyn FnMut(&CustomDraw)>;
+
+pub struct GdiSurface {
+    dc: windef::HDC,
+    width: i32,
+    height: i32,
+    pens: PenCache,
+    state: DcState,
+    custom: Vec<CustomDrawCallback>,
+    scratch: Option<Scratch>,
+    scale: i32,
+    scaled_fonts: HashMap<windef::HFONT, windef::HFONT>,
+    fill_rule: FillRule,
+    text_overflow: TextOverflow,
+    text_background: TextBackground,
+    // reused by `fill_polygon` so large polygons do not allocate every frame
+    points: Vec<windef::POINT>,
+    // `(left, top, right, bottom)` in unscaled units; `clip` is what is currently applied to the DC, in device pixels
+    outer_clip: Option<(i32, i32, i32, i32)>,
+    clip: Option<(i32, i32, i32, i32)>,
+}
+
+impl GdiSurface {
+    pub unsafe fn new(dc: windef::HDC, width: i32, height: i32) -> GdiSurface {
+        GdiSurface {
+            dc: dc,
+            width: width,
+            height: height,
+            pens: PenCache::new(DEFAULT_PEN_CACHE_CAPACITY),
+            state: DcState::default(),
+            custom: Vec::new(),
+            scratch: None,
+            scale: 1,
+            scaled_fonts: HashMap::new(),
+            fill_rule: FillRule::default(),
+            text_overflow: TextOverflow::default(),
+            text_background: TextBackground::default(),
+            points: Vec::new(),
+            outer_clip: None,
+            clip: None,
+        }
+    }
+
+    pub fn dc(&self) -> windef::HDC {
+        self.dc
+    }
+
+    pub fn pen_cache_stats(&self) -> PenCacheStats {
+        self.pens.stats()
+    }
+
+    pub fn set_pen_cache_capacity(&mut self, capacity: usize) {
+        self.pens.capacity = capacity.max(1);
+        unsafe { self.pens.evict(self.dc, &mut self.state, self.pens.capacity) }
+    }
+
+    pub fn flush_pen_cache(&mut self) {
+        unsafe { self.pens.flush(self.dc, &mut self.state) }
+    }
+
+    /// Number of `SelectObject`/`Set*`/clip calls issued since the last `reset_state_changes`.
+    pub fn state_changes(&self) -> u32 {
+        self.state.changes
+    }
+
+    pub fn reset_state_changes(&mut self) {
+        self.state.changes = 0;
+    }
+
+    /// Registers a callback invoked, in registration order, for every custom command.
+    pub fn add_custom_callback(&mut self, callback: CustomDrawCallback) {
+        self.custom.push(callback);
+    }
+
+    pub fn clear_custom_callbacks(&mut self) {
+        self.custom.clear();
+    }
+
+    /// Forgets the tracked DC state; call it after drawing on the DC behind the surface's back.
+    pub fn invalidate_state(&mut self) {
+        self.state.invalidate();
+    }
+
+    /// Join and cap of strokes thicker than one pixel.
+    pub fn set_stroke_style(&mut self, join: LineJoin, cap: LineCap) {
+        self.pens.stroke_style = nk_gdi_pen_style(join, cap);
+    }
+
+    /// Rule for filled polygons; GDI's default is `FillRule::Alternate`.
+    pub fn set_fill_rule(&mut self, rule: FillRule) {
+        self.fill_rule = rule;
+    }
+
+    pub fn fill_rule(&self) -> FillRule {
+        self.fill_rule
+    }
+
+    pub fn set_text_overflow(&mut self, overflow: TextOverflow) {
+        self.text_overflow = overflow;
+    }
+
+    pub fn text_overflow(&self) -> TextOverflow {
+        self.text_overflow
+    }
+
+    pub fn set_text_background(&mut self, background: TextBackground) {
+        self.text_background = background;
+    }
+
+    pub fn text_background(&self) -> TextBackground {
+        self.text_background
+    }
+
+    /// Limits everything drawn, clears included, to `clip`. Applied from the next `clear` or `reset_clip`.
+    pub fn set_outer_clip(&mut self, clip: Option<windef::RECT>) {
+        self.outer_clip = clip.map(|r| (r.left, r.top, r.right, r.bottom));
+    }
+
+    pub fn outer_clip(&self) -> Option<windef::RECT> {
+        self.outer_clip.map(|(left, top, right, bottom)| windef::RECT { left: left, top: top, right: right, bottom: bottom })
+    }
+
+    /// The clip rectangle currently applied to the DC, in device pixels.
+    pub fn clip(&self) -> Option<windef::RECT> {
+        self.clip.map(|(left, top, right, bottom)| windef::RECT { left: left, top: top, right: right, bottom: bottom })
+    }
+
+    /// Drops any scissor and sets the DC clip to just the outer clip. The DC is updated even if the tracked state
+    /// says it is already there, so a clip left behind by a previous frame or by other code never survives.
+    pub fn reset_clip(&mut self) {
+        self.state.clip = None;
+        let outer = self.scaled_outer_clip();
+        unsafe { self.apply_clip(outer) }
+    }
+
+    fn scaled_outer_clip(&self) -> Option<(i32, i32, i32, i32)> {
+        let s = self.scale;
+        self.outer_clip.map(|(l, t, r, b)| (l * s, t * s, r * s, b * s))
+    }
+
+    unsafe fn apply_clip(&mut self, clip: Option<(i32, i32, i32, i32)>) {
+        self.clip = clip;
+        self.state.set_clip(self.dc, clip);
+    }
+
+    /// Switches drawing to another DC of the given size. Cached pens, the clip and the selected font are released from the old one first.
+    pub unsafe fn retarget(&mut self, dc: windef::HDC, width: i32, height: i32) {
+        self.pens.flush(self.dc, &mut self.state);
+        wingdi::SelectClipRgn(self.dc, ptr::null_mut());
+        // fonts are only deselected from the current DC later on, so the old one must not keep any
+        self.deselect_fonts();
+        self.release_scaled_fonts();
+        self.state.invalidate();
+        self.dc = dc;
+        self.width = width;
+        self.height = height;
+        self.clip = None;
+    }
+
+    /// Tells the surface that coordinates reaching it are multiplied by `scale` (see `supersample::Scaled`), so fonts are drawn that much larger.
+    pub fn set_scale(&mut self, scale: i32) {
+        if scale != self.scale {
+            unsafe { self.release_scaled_fonts() }
+            self.scale = scale.max(1);
+        }
+    }
+
+    pub fn scale(&self) -> i32 {
+        self.scale
+    }
+
+    // The font to draw with at the current scale; scaled copies are created on first use.
+    unsafe fn font_handle(&mut self, font: windef::HFONT) -> windef::HFONT {
+        if self.scale == 1 {
+            return font;
+        }
+
+        let scale = self.scale;
+        *self.scaled_fonts.entry(font).or_insert_with(|| {
+            let mut logfont: wingdi::LOGFONTW = mem::zeroed();
+            wingdi::GetObjectW(font as *mut c_void, mem::size_of::<wingdi::LOGFONTW>() as i32, &mut logfont as *mut _ as *mut c_void);
+            logfont.lfHeight *= scale;
+            logfont.lfWidth *= scale;
+            wingdi::CreateFontIndirectW(&logfont)
+        })
+    }
+
+    // Lets go of a font about to be deleted: it is deselected everywhere and its scaled copy is deleted.
+    unsafe fn forget_font(&mut self, font: windef::HFONT) {
+        self.deselect_fonts();
+        if let Some(scaled) = self.scaled_fonts.remove(&font) {
+            wingdi::DeleteObject(scaled as *mut c_void);
+        }
+    }
+
+    // A font still selected into a DC cannot be deleted, so the target and the scratch DIB go back to the stock font.
+    unsafe fn deselect_fonts(&mut self) {
+        let stock = wingdi::GetStockObject(wingdi::SYSTEM_FONT as i32);
+        self.state.select_font(self.dc, stock);
+        if let Some(ref mut scratch) = self.scratch {
+            scratch.state.select_font(scratch.dib.dc, stock);
+        }
+    }
+
+    unsafe fn release_scaled_fonts(&mut self) {
+        if self.scaled_fonts.is_empty() {
+            return;
+        }
+
+        self.deselect_fonts();
+        for (_, scaled) in self.scaled_fonts.drain() {
+            wingdi::DeleteObject(scaled as *mut c_void);
+        }
+    }
+
+    // Runs `draw` with `col` straight on the DC when it is opaque, skips it when fully transparent, and otherwise draws it
+    // in white into the scratch DIB and composites the coverage with `AlphaBlend`. `bounds` is `(left, top, right, bottom)`.
+    unsafe fn blended<F>(&mut self, bounds: (i32, i32, i32, i32), line_thickness: i32, col: Color, draw: F)
+    where
+        F: FnOnce(windef::HDC, &mut DcState, &mut PenCache, Color),
+    {
+        match col.a {
+            0 => {}
+            255 => draw(self.dc, &mut self.state, &mut self.pens, col),
+            _ => self.composite(bounds, line_thickness, Tint::Coverage(col), |dc, st, pens| draw(dc, st, pens, Color { r: 255, g: 255, b: 255, a: 255 })),
+        }
+    }
+
+    unsafe fn composite<F>(&mut self, bounds: (i32, i32, i32, i32), line_thickness: i32, tint: Tint, draw: F)
+    where
+        F: FnOnce(windef::HDC, &mut DcState, &mut PenCache),
+    {
+        // miter joins reach up to `MITER_LIMIT` half widths past their vertex before GDI bevels them, further than any cap
+        let pad = if line_thickness > 1 { (line_thickness as f32 / 2.0 * surface::MITER_LIMIT).ceil() as i32 + 1 } else { 1 };
+        let (x0, y0) = ((bounds.0 - pad).max(0), (bounds.1 - pad).max(0));
+        let (x1, y1) = ((bounds.2 + pad).min(self.width), (bounds.3 + pad).min(self.height));
+        let (w, h) = (x1 - x0, y1 - y0);
+        if w <= 0 || h <= 0 {
+            return;
+        }
+
+        let fits = self.scratch.as_ref().map_or(false, |s| s.dib.width >= w && s.dib.height >= h);
+        if !fits {
+            let (sw, sh) = self.scratch.as_ref().map_or((w, h), |s| (s.dib.width.max(w), s.dib.height.max(h)));
+            self.scratch = None;
+            self.scratch = Scratch::new(sw, sh);
+        }
+        // without memory for the scratch buffer the translucent primitive is dropped
+        let scratch = match self.scratch.as_mut() {
+            Some(scratch) => scratch,
+            None => return,
+        };
+        scratch.pens.stroke_style = self.pens.stroke_style;
+
+        scratch.begin(x0, y0, w, h);
+        draw(scratch.dib.dc, &mut scratch.state, &mut scratch.pens);
+        scratch.finish(w, h, tint);
+
+        let blendfunc = wingdi::BLENDFUNCTION {
+            BlendOp: wingdi::AC_SRC_OVER,
+            BlendFlags: 0,
+            SourceConstantAlpha: 255,
+            AlphaFormat: wingdi::AC_SRC_ALPHA,
+        };
+        wingdi::GdiAlphaBlend(self.dc, x0, y0, w, h, scratch.dib.dc, 0, 0, w, h, blendfunc);
+    }
+}
+
+// `(left, top, right, bottom)` covering every point.
+fn nk_gdi_bounds<I: IntoIterator<Item = (i32, i32)>>(points: I) -> (i32, i32, i32, i32) {
+    points.into_iter().fold((i32::max_value(), i32::max_value(), i32::min_value(), i32::min_value()), |b, (x, y)| (b.0.min(x), b.1.min(y), b.2.max(x + 1), b.3.max(y + 1)))
+}
+
+impl Drop for GdiSurface {
+    fn drop(&mut self) {
+        unsafe {
+            self.pens.flush(self.dc, &mut self.state);
+            self.deselect_fonts();
+            self.release_scaled_fonts();
+        }
+    }
+}
+
+#[cfg(any(test, feature = "conformance"))]
+impl Readback for GdiSurface {
+    fn read_pixel(&mut self, x: i32, y: i32) -> [u8; 4] {
+        let c = unsafe { wingdi::GetPixel(self.dc, x, y) };
+        [(c & 0xff) as u8, ((c >> 8) & 0xff) as u8, ((c >> 16) & 0xff) as u8, 255]
+    }
+}
+
+impl Surface for GdiSurface {
+    fn clear(&mut self, col: Color) {
+        self.reset_clip();
+        unsafe { nk_gdi_clear(self.dc, &mut self.state, self.width, self.height, col) }
+    }
+
+    fn scissor(&mut self, x: i32, y: i32, w: i32, h: i32) {
+        let mut clip = (x, y, x + w, y + h);
+        if let Some((l, t, r, b)) = self.scaled_outer_clip() {
+            clip = (clip.0.max(l), clip.1.max(t), clip.2.min(r), clip.3.min(b));
+        }
+        // a scissor entirely outside the outer clip leaves nothing drawable
+        clip = (clip.0, clip.1, clip.2.max(clip.0), clip.3.max(clip.1));
+        unsafe { self.apply_clip(Some(clip)) }
+    }
+
+    fn stroke_line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, line_thickness: i32, col: Color) {
+        let bounds = nk_gdi_bounds([(x0, y0), (x1, y1)].iter().cloned());
+        unsafe { self.blended(bounds, line_thickness, col, |dc, st, pens, col| nk_gdi_stroke_line(dc, st, pens, x0, y0, x1, y1, line_thickness, col)) }
+    }
+
+    fn stroke_rect(&mut self, x: i32, y: i32, w: i32, h: i32, r: i32, line_thickness: i32, col: Color) {
+        unsafe { self.blended((x, y, x + w, y + h), line_thickness, col, |dc, st, pens, col| nk_gdi_stroke_rect(dc, st, pens, x, y, w, h, r, line_thickness, col)) }
+    }
+
+    fn fill_rect(&mut self, x: i32, y: i32, w: i32, h: i32, r: i32, col: Color) {
+        unsafe { self.blended((x, y, x + w, y + h), 0, col, |dc, st, _, col| nk_gdi_fill_rect(dc, st, x, y, w, h, r, col)) }
+    }
+
+    fn fill_rect_multi_color(&mut self, x: i32, y: i32, w: i32, h: i32, left: Color, top: Color, right: Color, bottom: Color) {
+        let alphas = [left.a, top.a, right.a, bottom.a];
+        unsafe {
+            if alphas.iter().all(|&a| a == 255) {
+                nk_gdi_fill_rect_multi_color(self.dc, x, y, w, h, left, top, right, bottom);
+            } else if alphas.iter().any(|&a| a > 0) {
+                self.composite((x, y, x + w, y + h), 0, Tint::Premultiply, |dc, _, _| nk_gdi_fill_rect_multi_color(dc, x, y, w, h, left, top, right, bottom));
+            }
+        }
+    }
+
+    fn stroke_triangle(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, x2: i32, y2: i32, line_thickness: i32, col: Color) {
+        let bounds = nk_gdi_bounds([(x0, y0), (x1, y1), (x2, y2)].iter().cloned());
+        unsafe { self.blended(bounds, line_thickness, col, |dc, st, pens, col| nk_gdi_stroke_triangle(dc, st, pens, x0, y0, x1, y1, x2, y2, line_thickness, col)) }
+    }
+
+    fn fill_triangle(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, x2: i32, y2: i32, col: Color) {
+        let bounds = nk_gdi_bounds([(x0, y0), (x1, y1), (x2, y2)].iter().cloned());
+        unsafe { self.blended(bounds, 0, col, |dc, st, _, col| nk_gdi_fill_triangle(dc, st, x0, y0, x1, y1, x2, y2, col)) }
+    }
+
+    fn stroke_polygon(&mut self, points: &[Vec2i], line_thickness: i32, col: Color) {
+        let bounds = nk_gdi_bounds(points.iter().map(|p| (p.x as i32, p.y as i32)));
+        unsafe { self.blended(bounds, line_thickness, col, |dc, st, pens, col| nk_gdi_stroke_polygon(dc, st, pens, points, line_thickness, col)) }
+    }
+
+    fn fill_polygon(&mut self, points: &[Vec2i], col: Color) {
+        let bounds = nk_gdi_bounds(points.iter().map(|p| (p.x as i32, p.y as i32)));
+        let (rule, mut buffer) = (self.fill_rule, mem::replace(&mut self.points, Vec::new()));
+        unsafe { self.blended(bounds, 0, col, |dc, st, _, col| nk_gdi_fill_polygon(dc, st, &mut buffer, points, rule, col)) }
+        self.points = buffer;
+    }
+
+    fn stroke_polyline(&mut self, points: &[Vec2i], line_thickness: i32, col: Color) {
+        let bounds = nk_gdi_bounds(points.iter().map(|p| (p.x as i32, p.y as i32)));
+        unsafe { self.blended(bounds, line_thickness, col, |dc, st, pens, col| nk_gdi_stroke_polyline(dc, st, pens, points, line_thickness, col)) }
+    }
+
+    fn stroke_arc(&mut self, cx: i32, cy: i32, r: u32, a1: f32, a2: f32, line_thickness: i32, col: Color) {
+        let ri = r as i32;
+        unsafe { self.blended((cx - ri, cy - ri, cx + ri, cy + ri), line_thickness, col, |dc, st, pens, col| nk_gdi_stroke_arc(dc, st, pens, cx, cy, r, a1, a2, line_thickness, col)) }
+    }
+
+    fn fill_arc(&mut self, cx: i32, cy: i32, r: u32, a1: f32, a2: f32, col: Color) {
+        let ri = r as i32;
+        unsafe { self.blended((cx - ri, cy - ri, cx + ri, cy + ri), 0, col, |dc, st, _, col| nk_gdi_fill_arc(dc, st, cx, cy, r, a1, a2, col)) }
+    }
+
+    fn stroke_circle(&mut self, x: i32, y: i32, w: i32, h: i32, line_thickness: i32, col: Color) {
+        unsafe { self.blended((x, y, x + w, y + h), line_thickness, col, |dc, st, pens, col| nk_gdi_stroke_circle(dc, st, pens, x, y, w, h, line_thickness, col)) }
+    }
+
+    fn fill_circle(&mut self, x: i32, y: i32, w: i32, h: i32, col: Color) {
+        unsafe { self.blended((x, y, x + w, y + h), 0, col, |dc, st, _, col| nk_gdi_fill_circle(dc, st, x, y, w, h, col)) }
+    }
+
+    fn stroke_curve(&mut self, p1: Vec2i, p2: Vec2i, p3: Vec2i, p4: Vec2i, line_thickness: i32, col: Color) {
+        // a bezier stays inside the hull of its control points
+        let bounds = nk_gdi_bounds([p1, p2, p3, p4].iter().map(|p| (p.x as i32, p.y as i32)));
+        unsafe { self.blended(bounds, line_thickness, col, |dc, st, pens, col| nk_gdi_stroke_curve(dc, st, pens, p1, p2, p3, p4, line_thickness, col)) }
+    }
+
+    fn draw_image(&mut self, x: i32, y: i32, w: i32, h: i32, img: Image, col: Color) {
+        if col.a > 0 {
+            unsafe { nk_gdi_draw_image(self.dc, x, y, w, h, img, col) }
+        }
+    }
+
+    fn draw_text(&mut self, x: i32, y: i32, w: i32, h: i32, text: &[u8], font: &UserFont, cbg: Color, cfg: Color) {
+        unsafe {
+            let font = self.font_handle((*(font.userdata_ptr().ptr().unwrap() as *const GdiFont)).handle);
+            let (text, len) = (text.as_ptr() as *const i8, text.len() as i32);
+            let overflow = self.text_overflow;
+            let cbg = match self.text_background {
+                TextBackground::FromCommand => cbg,
+                TextBackground::Transparent => Color { a: 0, ..cbg },
+            };
+
+            // solid or no background over solid text needs no compositing
+            if cfg.a == 255 && (cbg.a == 255 || cbg.a == 0) {
+                let background = if cbg.a == 255 { Some(cbg) } else { None };
+                nk_gdi_draw_text(self.dc, &mut self.state, x, y, w, h, text, len, font, background, cfg, overflow);
+                return;
+            }
+
+            let size = nk_gdi_text_extent(self.dc, &mut self.state, font, text, len);
+            let (tw, th) = (size.cx.min(w), size.cy.min(h));
+            let bounds = (x, y, x + tw, y + th);
+            self.blended(bounds, 0, cbg, |dc, st, _, col| nk_gdi_fill_rect(dc, st, x, y, tw, th, 0, col));
+            self.blended(bounds, 0, cfg, |dc, st, _, col| nk_gdi_draw_text(dc, st, x, y, w, h, text, len, font, None, col, overflow));
+        }
+    }
+
+    fn draw_custom(&mut self, x: i32, y: i32, w: i32, h: i32, data: Handle) {
+        if self.custom.is_empty() {
+            return;
+        }
+
+        let draw = CustomDraw {
+            dc: self.dc,
+            rect: windef::RECT { left: x, top: y, right: x + w, bottom: y + h },
+            clip: self.clip(),
+            data: data,
+            scale: self.scale,
+        };
+        unsafe {
+            let saved = wingdi::SaveDC(self.dc);
+            for callback in &mut self.custom {
+                callback(&draw);
+            }
+            wingdi::RestoreDC(self.dc, saved);
+        }
+    }
+}
+
+unsafe fn nk_gdi_clear(dc: windef::HDC, st: &mut DcState, width: i32, height: i32, col: Color) {
+    let color = convert_color(col);
+    let rect = windef::RECT {
+        left: 0,
+        top: 0,
+        right: width,
+        bottom: height,
+    };
+    st.set_bk_color(dc, color);
+
+    wingdi::ExtTextOutW(dc, 0, 0, wingdi::ETO_OPAQUE, &rect, ptr::null_mut(), 0, ptr::null_mut());
+}
+
+unsafe fn nk_gdi_select_stroke(dc: windef::HDC, st: &mut DcState, pens: &mut PenCache, line_thickness: i32, color: windef::COLORREF) {
+    st.select_brush(dc, wingdi::GetStockObject(wingdi::NULL_BRUSH as i32));
+
+    if line_thickness == 1 {
+        st.select_pen(dc, wingdi::GetStockObject(wingdi::DC_PEN as i32));
+        st.set_pen_color(dc, color);
+    } else {
+        let style = pens.stroke_style;
+        let pen = pens.get(dc, st, color, line_thickness, style);
+        st.select_pen(dc, pen as windef::HGDIOBJ);
+    }
+}
+
+unsafe fn nk_gdi_stroke_line(dc: windef::HDC, st: &mut DcState, pens: &mut PenCache, x0: i32, y0: i32, x1: i32, y1: i32, line_thickness: i32, col: Color) {
+    nk_gdi_select_stroke(dc, st, pens, line_thickness, convert_color(col));
+
+    wingdi::MoveToEx(dc, x0, y0, ptr::null_mut());
+    wingdi::LineTo(dc, x1, y1);
+}
+
+unsafe fn nk_gdi_stroke_rect(dc: windef::HDC, st: &mut DcState, pens: &mut PenCache, x: i32, y: i32, w: i32, h: i32, r: i32, line_thickness: i32, col: Color) {
+    nk_gdi_select_stroke(dc, st, pens, line_thickness, convert_color(col));
+
+    // RoundRect takes the size of the corner ellipse, i.e. the diameter
+    let r = surface::corner_radius(w, h, r);
+    if r == 0 {
+        wingdi::Rectangle(dc, x, y, x + w, y + h);
+    } else {
+        wingdi::RoundRect(dc, x, y, x + w, y + h, r * 2, r * 2);
+    }
+}
+
+unsafe fn nk_gdi_fill_rect(dc: windef::HDC, st: &mut DcState, x: i32, y: i32, w: i32, h: i32, r: i32, col: Color) {
+    let color = convert_color(col);
+
+    let r = surface::corner_radius(w, h, r);
+    if r == 0 {
+        let rect = windef::RECT { left: x, top: y, right: x + w, bottom: y + h };
+        st.set_bk_color(dc, color);
+        wingdi::ExtTextOutW(dc, 0, 0, wingdi::ETO_OPAQUE, &rect, ptr::null_mut(), 0, ptr::null_mut());
+    } else {
+        st.select_fill(dc, color);
+        wingdi::RoundRect(dc, x, y, x + w, y + h, r * 2, r * 2);
+    }
+}
+
+fn nk_gdi_vertex(x: i32, y: i32, col: Color) -> wingdi::TRIVERTEX {
+    wingdi::TRIVERTEX {
+        x: x,
+        y: y,
+        Red: (col.r as u16) << 8,
+        Green: (col.g as u16) << 8,
+        Blue: (col.b as u16) << 8,
+        Alpha: (col.a as u16) << 8,
+    }
+}
+
+// GDI only interpolates linearly across triangles, so the rect is split into four around a center vertex carrying the average color.
+unsafe fn nk_gdi_fill_rect_multi_color(dc: windef::HDC, x: i32, y: i32, w: i32, h: i32, left: Color, top: Color, right: Color, bottom: Color) {
+    let center = Color {
+        r: ((left.r as u32 + top.r as u32 + right.r as u32 + bottom.r as u32) / 4) as u8,
+        g: ((left.g as u32 + top.g as u32 + right.g as u32 + bottom.g as u32) / 4) as u8,
+        b: ((left.b as u32 + top.b as u32 + right.b as u32 + bottom.b as u32) / 4) as u8,
+        a: ((left.a as u32 + top.a as u32 + right.a as u32 + bottom.a as u32) / 4) as u8,
+    };
+    let mut vertices = [
+        nk_gdi_vertex(x, y, left),
+        nk_gdi_vertex(x + w, y, top),
+        nk_gdi_vertex(x + w, y + h, right),
+        nk_gdi_vertex(x, y + h, bottom),
+        nk_gdi_vertex(x + w / 2, y + h / 2, center),
+    ];
+    let mut mesh = [
+        wingdi::GRADIENT_TRIANGLE { Vertex1: 0, Vertex2: 1, Vertex3: 4 },
+        wingdi::GRADIENT_TRIANGLE { Vertex1: 1, Vertex2: 2, Vertex3: 4 },
+        wingdi::GRADIENT_TRIANGLE { Vertex1: 2, Vertex2: 3, Vertex3: 4 },
+        wingdi::GRADIENT_TRIANGLE { Vertex1: 3, Vertex2: 0, Vertex3: 4 },
+    ];
+
+    wingdi::GdiGradientFill(dc, vertices.as_mut_ptr(), vertices.len() as u32, mesh.as_mut_ptr() as *mut c_void, mesh.len() as u32, wingdi::GRADIENT_FILL_TRIANGLE);
+}
+
+unsafe fn nk_gdi_fill_triangle(dc: windef::HDC, st: &mut DcState, x0: i32, y0: i32, x1: i32, y1: i32, x2: i32, y2: i32, col: Color) {
+    let points = [windef::POINT { x: x0, y: y0 }, windef::POINT { x: x1, y: y1 }, windef::POINT { x: x2, y: y2 }];
+
+    st.select_fill(dc, convert_color(col));
+    wingdi::Polygon(dc, &points[0] as *const windef::POINT, points.len() as i32);
+}
+
+unsafe fn nk_gdi_stroke_triangle(dc: windef::HDC, st: &mut DcState, pens: &mut PenCache, x0: i32, y0: i32, x1: i32, y1: i32, x2: i32, y2: i32, line_thickness: i32, col: Color) {
+    nk_gdi_select_stroke(dc, st, pens, line_thickness, convert_color(col));
+    nk_gdi_stroke_path(dc, [(x0, y0), (x1, y1), (x2, y2)].iter().cloned(), true);
+}
+
+// Strokes the points as one figure, so thick pens get proper joins, including the closing one, instead of overlapping segment ends.
+unsafe fn nk_gdi_stroke_path<I: Iterator<Item = (i32, i32)>>(dc: windef::HDC, mut points: I, closed: bool) {
+    let (x, y) = match points.next() {
+        Some(first) => first,
+        None => return,
+    };
+
+    wingdi::BeginPath(dc);
+    wingdi::MoveToEx(dc, x, y, ptr::null_mut());
+    for (x, y) in points {
+        wingdi::LineTo(dc, x, y);
+    }
+    if closed {
+        wingdi::CloseFigure(dc);
+    }
+    wingdi::EndPath(dc);
+    wingdi::StrokePath(dc);
+}
+
+// `points` is scratch space; it keeps its capacity between calls.
+unsafe fn nk_gdi_fill_polygon(dc: windef::HDC, st: &mut DcState, points: &mut Vec<windef::POINT>, pnts: &[Vec2i], rule: FillRule, col: Color) {
+    if pnts.is_empty() {
+        return;
+    }
+
+    points.clear();
+    points.extend(pnts.iter().map(|p| windef::POINT { x: p.x as i32, y: p.y as i32 }));
+
+    st.select_fill(dc, convert_color(col));
+    st.set_poly_fill_mode(
+        dc,
+        match rule {
+            FillRule::Alternate => wingdi::ALTERNATE,
+            FillRule::Winding => wingdi::WINDING,
+        },
+    );
+    wingdi::Polygon(dc, points.as_ptr(), points.len() as i32);
+}
+
+unsafe fn nk_gdi_stroke_polygon(dc: windef::HDC, st: &mut DcState, pens: &mut PenCache, pnts: &[Vec2i], line_thickness: i32, col: Color) {
+    nk_gdi_select_stroke(dc, st, pens, line_thickness, convert_color(col));
+    nk_gdi_stroke_path(dc, pnts.iter().map(|p| (p.x as i32, p.y as i32)), true);
+}
+
+unsafe fn nk_gdi_stroke_polyline(dc: windef::HDC, st: &mut DcState, pens: &mut PenCache, pnts: &[Vec2i], line_thickness: i32, col: Color) {
+    nk_gdi_select_stroke(dc, st, pens, line_thickness, convert_color(col));
+    nk_gdi_stroke_path(dc, pnts.iter().map(|p| (p.x as i32, p.y as i32)), false);
+}
+
+// Bounding box and radial end points for `Arc`/`Pie` from a nuklear start angle and end angle, in radians growing clockwise (y down).
+// `None` for an empty sweep; a sweep of a full turn or more gives coinciding radials, which GDI draws as a whole ellipse.
+unsafe fn nk_gdi_arc_geometry(dc: windef::HDC, st: &mut DcState, cx: i32, cy: i32, r: u32, a1: f32, a2: f32) -> Option<[i32; 8]> {
+    // radials only give a direction, so they are placed far out to keep the rounding error small
+    const RADIAL: f32 = 1024.0;
+
+    let sweep = a2 - a1;
+    if sweep.abs() < ::std::f32::EPSILON {
+        return None;
+    }
+
+    let r = r as i32;
+    let end = if sweep.abs() >= 2.0 * ::std::f32::consts::PI { a1 } else { a2 };
+    let radial = |a: f32| ((a.cos() * RADIAL).round() as i32 + cx, (a.sin() * RADIAL).round() as i32 + cy);
+    let ((x3, y3), (x4, y4)) = (radial(a1), radial(end));
+
+    let direction = if sweep > 0.0 { wingdi::AD_CLOCKWISE } else { wingdi::AD_COUNTERCLOCKWISE };
+    st.set_arc_direction(dc, direction as i32);
+    Some([cx - r, cy - r, cx + r, cy + r, x3, y3, x4, y4])
+}
+
+unsafe fn nk_gdi_fill_arc(dc: windef::HDC, st: &mut DcState, cx: i32, cy: i32, r: u32, a1: f32, a2: f32, col: Color) {
+    st.select_fill(dc, convert_color(col));
+
+    if let Some([x1, y1, x2, y2, x3, y3, x4, y4]) = nk_gdi_arc_geometry(dc, st, cx, cy, r, a1, a2) {
+        wingdi::Pie(dc, x1, y1, x2, y2, x3, y3, x4, y4);
+    }
+}
+
+unsafe fn nk_gdi_stroke_arc(dc: windef::HDC, st: &mut DcState, pens: &mut PenCache, cx: i32, cy: i32, r: u32, a1: f32, a2: f32, line_thickness: i32, col: Color) {
+    nk_gdi_select_stroke(dc, st, pens, line_thickness, convert_color(col));
+
+    if let Some([x1, y1, x2, y2, x3, y3, x4, y4]) = nk_gdi_arc_geometry(dc, st, cx, cy, r, a1, a2) {
+        wingdi::Arc(dc, x1, y1, x2, y2, x3, y3, x4, y4);
+    }
+}
+
+unsafe fn nk_gdi_fill_circle(dc: windef::HDC, st: &mut DcState, x: i32, y: i32, w: i32, h: i32, col: Color) {
+    st.select_fill(dc, convert_color(col));
+    wingdi::Ellipse(dc, x, y, x + w, y + h);
+}
+
+unsafe fn nk_gdi_stroke_circle(dc: windef::HDC, st: &mut DcState, pens: &mut PenCache, x: i32, y: i32, w: i32, h: i32, line_thickness: i32, col: Color) {
+    nk_gdi_select_stroke(dc, st, pens, line_thickness, convert_color(col));
+    wingdi::Ellipse(dc, x, y, x + w, y + h);
+}
+
+unsafe fn nk_gdi_stroke_curve(dc: windef::HDC, st: &mut DcState, pens: &mut PenCache, p1: Vec2i, p2: Vec2i, p3: Vec2i, p4: Vec2i, line_thickness: i32, col: Color) {
+    let p = [
+        windef::POINT { x: p1.x as i32, y: p1.y as i32 },
+        windef::POINT { x: p2.x as i32, y: p2.y as i32 },
+        windef::POINT { x: p3.x as i32, y: p3.y as i32 },
+        windef::POINT { x: p4.x as i32, y: p4.y as i32 },
+    ];
+
+    nk_gdi_select_stroke(dc, st, pens, line_thickness, convert_color(col));
+    wingdi::PolyBezier(dc, &p[0], p.len() as u32);
+}
+
+// `col.a` fades the whole image; its color channels are ignored.
+unsafe fn nk_gdi_draw_image(dc: windef::HDC, x: i32, y: i32, w: i32, h: i32, mut img: Image, col: Color) {
+    let mut bitmap: wingdi::BITMAP = mem::zeroed();
+    let hdc1 = wingdi::CreateCompatibleDC(ptr::null_mut());
+    let h_bitmap = img.ptr() as *mut _ as *mut ctypes::c_void;
+
+    wingdi::GetObjectW(h_bitmap, mem::size_of_val(&bitmap) as i32, &mut bitmap as *mut _ as *mut ctypes::c_void);
+    log_error();
+    wingdi::SelectObject(hdc1, h_bitmap);
+    log_error();
+
+    let blendfunc = wingdi::BLENDFUNCTION {
+        BlendOp: wingdi::AC_SRC_OVER,
+        BlendFlags: 0,
+        SourceConstantAlpha: col.a,
+        AlphaFormat: wingdi::AC_SRC_ALPHA,
+    };
+
+    wingdi::GdiAlphaBlend(dc, x, y, w, h, hdc1, 0, 0, bitmap.bmWidth, bitmap.bmHeight, blendfunc);
+    log_error();
+    wingdi::DeleteDC(hdc1);
+}
+
+fn nk_gdi_wide(text: *const i8, text_len: i32) -> Vec<u16> {
+    unsafe {
+        let wsize = stringapiset::MultiByteToWideChar(winnls::CP_UTF8, 0, text, text_len, ptr::null_mut(), 0);
+        let mut wstr = vec![0u16; wsize.max(0) as usize];
+        stringapiset::MultiByteToWideChar(winnls::CP_UTF8, 0, text, text_len, wstr.as_mut_ptr(), wsize);
+        wstr
+    }
+}
+
+unsafe fn nk_gdi_text_extent(dc: windef::HDC, st: &mut DcState, font: windef::HFONT, text: *const i8, text_len: i32) -> windef::SIZE {
+    let wstr = nk_gdi_wide(text, text_len);
+    let mut size = windef::SIZE { cx: 0, cy: 0 };
+    st.select_font(dc, font as *mut c_void);
+    wingdi::GetTextExtentPoint32W(dc, wstr.as_ptr(), wstr.len() as i32, &mut size);
+    size
+}
+
+// Without a background the text is drawn in `TRANSPARENT` mode. Either way it is clipped to `w` by `h`.
+unsafe fn nk_gdi_draw_text(dc: windef::HDC, st: &mut DcState, x: i32, y: i32, w: i32, h: i32, text: *const i8, text_len: i32, font: windef::HFONT, cbg: Option<Color>, cfg: Color, overflow: TextOverflow) {
+    let mut wstr = nk_gdi_wide(text, text_len);
+    let rect = windef::RECT { left: x, top: y, right: x + w, bottom: y + h };
+
+    st.select_font(dc, font as *mut c_void);
+    if overflow == TextOverflow::Ellipsis {
+        nk_gdi_ellipsize(dc, &mut wstr, w);
+    }
+
+    // `OPAQUE` mode fills the character cells only; `ETO_OPAQUE` would fill the whole clip rectangle
+    match cbg {
+        Some(cbg) => {
+            st.set_bk_mode(dc, wingdi::OPAQUE);
+            st.set_bk_color(dc, convert_color(cbg));
+        }
+        None => st.set_bk_mode(dc, wingdi::TRANSPARENT),
+    }
+    st.set_text_color(dc, convert_color(cfg));
+
+    wingdi::ExtTextOutW(dc, x, y, wingdi::ETO_CLIPPED, &rect, wstr.as_mut_ptr(), wstr.len() as u32, ptr::null_mut());
+}
+
+// Cuts `wstr` down to what fits in `width` pixels together with a trailing ellipsis; text that fits is left alone.
+// `dc` must have the font selected.
+unsafe fn nk_gdi_ellipsize(dc: windef::HDC, wstr: &mut Vec<u16>, width: i32) {
+    const ELLIPSIS: u16 = 0x2026;
+
+    let mut fit = 0;
+    let mut size = windef::SIZE { cx: 0, cy: 0 };
+    wingdi::GetTextExtentExPointW(dc, wstr.as_ptr(), wstr.len() as i32, width, &mut fit, ptr::null_mut(), &mut size);
+    if size.cx <= width {
+        return;
+    }
+
+    let mut ellipsis = windef::SIZE { cx: 0, cy: 0 };
+    wingdi::GetTextExtentPoint32W(dc, &ELLIPSIS, 1, &mut ellipsis);
+    wingdi::GetTextExtentExPointW(dc, wstr.as_ptr(), wstr.len() as i32, (width - ellipsis.cx).max(0), &mut fit, ptr::null_mut(), &mut size);
+
+    let mut fit = (fit.max(0) as usize).min(wstr.len());
+    // do not leave half of a surrogate pair behind
+    if fit > 0 && (0xd800..0xdc00).contains(&wstr[fit - 1]) {
+        fit -= 1;
+    }
+    wstr.truncate(fit);
+    wstr.push(ELLIPSIS);
+}
+
+unsafe extern "C" fn nk_gdifont_get_text_width(handle: nksys::nk_handle, _: f32, text: *const i8, len: i32) -> f32 {
+    let font = handle.ptr as *const GdiFont;
+    if font.is_null() || text.is_null() {
+        return 0.0;
+    }
+
+    let mut size = windef::SIZE { cx: 0, cy: 0 };
+    let wsize = stringapiset::MultiByteToWideChar(winnls::CP_UTF8, 0, text, len, ptr::null_mut(), 0);
+    let mut wstr: Vec<ctypes::wchar_t> = vec![0; wsize as usize];
+    stringapiset::MultiByteToWideChar(winnls::CP_UTF8, 0, text, len, wstr.as_mut_slice() as *mut _ as *mut ctypes::wchar_t, wsize);
+
+    if wingdi::GetTextExtentPoint32W((*font).dc, wstr.as_slice() as *const _ as *const ctypes::wchar_t, wsize, &mut size) > 0 {
+        size.cx as f32
+    } else {
+        -1.0
+    }
+}
+
+unsafe extern "C" fn nk_gdi_clipbard_paste(_: nksys::nk_handle, edit: *mut nksys::nk_text_edit) {
+    if winuser::IsClipboardFormatAvailable(winuser::CF_UNICODETEXT) > 0 && winuser::OpenClipboard(ptr::null_mut()) > 0 {
+        let clip = winuser::GetClipboardData(winuser::CF_UNICODETEXT);
+        if !clip.is_null() {
+            let size = winbase::GlobalSize(clip) - 1;
+            if size > 0 {
+                let wstr = winbase::GlobalLock(clip);
+                if !wstr.is_null() {
+                    let size = (size / mem::size_of::<ctypes::wchar_t>() as basetsd::SIZE_T) as i32;
+                    let utf8size = stringapiset::WideCharToMultiByte(winnls::CP_UTF8, 0, wstr as *const u16, size, ptr::null_mut(), 0, ptr::null_mut(), ptr::null_mut());
+                    if utf8size > 0 {
+                        let mut utf8: Vec<u8> = vec![0; utf8size as usize];
+                        stringapiset::WideCharToMultiByte(winnls::CP_UTF8, 0, wstr as *const u16, size, utf8.as_mut_ptr() as *mut i8, utf8size, ptr::null_mut(), ptr::null_mut());
+                        let edit = &mut *(edit as *mut _ as *mut TextEdit);
+                        edit.paste(str::from_utf8_unchecked(utf8.as_slice()));
+                    }
+                    winbase::GlobalUnlock(clip);
+                }
+            }
+        }
+        winuser::CloseClipboard();
+    }
+}
+
+unsafe extern "C" fn nk_gdi_clipbard_copy(_: nksys::nk_handle, text: *const i8, _: i32) {
+    if winuser::OpenClipboard(ptr::null_mut()) > 0 {
+        let str_size = ffi::CStr::from_ptr(text).to_bytes().len() as i32;
+        let wsize = stringapiset::MultiByteToWideChar(winnls::CP_UTF8, 0, text, str_size, ptr::null_mut(), 0);
+        if wsize > 0 {
+            let mem = winbase::GlobalAlloc(2, ((wsize + 1) as usize * mem::size_of::<ctypes::wchar_t>()) as basetsd::SIZE_T); // 2 = GMEM_MOVEABLE
+            if !mem.is_null() {
+                let wstr = winbase::GlobalLock(mem);
+                if !wstr.is_null() {
+                    stringapiset::MultiByteToWideChar(winnls::CP_UTF8, 0, text, str_size, wstr as *mut u16, wsize);
+                    *(wstr.offset((wsize * mem::size_of::<u16>() as i32) as isize) as *mut u8) = 0;
+                    winbase::GlobalUnlock(mem);
+
+                    winuser::SetClipboardData(winuser::CF_UNICODETEXT, mem);
+                }
+            }
+        }
+        winuser::CloseClipboard();
+    }
+}
+
+#[cfg(feature = "own_window")]
+pub fn bundle(window_name: &str, width: u16, height: u16, font_name: &str, font_size: u16, allocator: &mut Allocator) -> Result<(Drawer, Context, GdiFontID), FontError> {
+    let (hwnd, hdc) = own_window::create_env(window_name, width, height);
+
+    let mut drawer = Drawer::new(hdc, width, height, Some(hwnd));
+
+    let font_id = drawer.new_font(&FontDescriptor::new(font_name, font_size))?;
+    let mut context = {
+        let font = drawer.font_by_id(font_id).unwrap();
+        Context::new(allocator, &font)
+    };
+    drawer.install_statics(&mut context);
+
+    Ok((drawer, context, font_id))
+}
+
+#[cfg(not(debug_assertions))]
+pub unsafe fn log_error() {}
+
+#[cfg(debug_assertions)]
+pub unsafe fn log_error() {
+    let error = errhandlingapi::GetLastError();
+    if error == 0 {
+        return;
+    }
+
+    let mut string = vec![0u16; 127];
+    winbase::FormatMessageW(
+        winbase::FORMAT_MESSAGE_FROM_SYSTEM | winbase::FORMAT_MESSAGE_IGNORE_INSERTS,
+        ptr::null_mut(),
+        error,
+        ntdef::LANG_SYSTEM_DEFAULT as u32,
+        string.as_mut_ptr(),
+        string.len() as u32,
+        ptr::null_mut(),
+    );
+
+    println!("Last error #{}: {}", error, std::string::String::from_utf16_lossy(&string));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conformance::{self, Mismatch, SIZE};
+
+    // GDI steps its curves in whole pixels while the reference samples pixel centers, so the two may split differently on
+    // centers within 1/20 px of the outline: 16 of them on the largest curve, the r = 20 circle.
+    const CURVE_TOLERANCE: usize = 16;
+    // GDI rounds the corners of its wide pens to whole pixels, which may move the two outline ends and the tip of a join and
+    // the two corners of a cap: 15 for the closed zigzag, the case with the most.
+    const STROKE_TOLERANCE: usize = 15;
+
+    fn check(cases: &[conformance::Case], tolerance: usize) -> Vec<Mismatch> {
+        unsafe {
+            let dib = Dib::new(SIZE as i32, SIZE as i32).expect("cannot allocate the test bitmap");
+            let mut surface = GdiSurface::new(dib.dc, SIZE as i32, SIZE as i32);
+            conformance::check(&mut surface, cases, tolerance)
+        }
+    }
+
+    fn key(color: windef::COLORREF) -> PenKey {
+        PenKey { color: color, width: 2, style: 0 }
+    }
+
+    // Stand-ins for pen handles; the bookkeeping never hands them to GDI.
+    fn fake_pen(n: usize) -> windef::HPEN {
+        n as windef::HPEN
+    }
+
+    #[test]
+    fn pen_cache_evicts_the_least_recently_used_pens_first() {
+        let mut cache = PenCache::new(3);
+        for n in 1..=3 {
+            assert_eq!(cache.lookup(key(n as u32)), None);
+            cache.insert(key(n as u32), fake_pen(n));
+        }
+        // 1 becomes the most recently used, leaving 2 and then 3 as the oldest
+        assert_eq!(cache.lookup(key(1)), Some(fake_pen(1)));
+
+        assert_eq!(cache.take_oldest(1), vec![fake_pen(2), fake_pen(3)]);
+        assert_eq!(cache.lookup(key(1)), Some(fake_pen(1)));
+        assert_eq!(cache.lookup(key(2)), None);
+        assert_eq!(cache.stats(), PenCacheStats { hits: 2, misses: 4, evictions: 2, live: 1 });
+    }
+
+    #[test]
+    fn pen_cache_stays_within_its_capacity() {
+        unsafe {
+            let dib = Dib::new(4, 4).expect("cannot allocate the test bitmap");
+            let mut st = DcState::default();
+            let mut cache = PenCache::new(4);
+            for color in 0..20 {
+                cache.get(dib.dc, &mut st, color, 3, cache.stroke_style);
+                assert!(cache.stats().live <= 4);
+            }
+            assert_eq!(cache.stats().evictions, 16);
+            cache.flush(dib.dc, &mut st);
+        }
+    }
+
+    #[test]
+    fn evicted_pens_are_deselected_before_they_are_deleted() {
+        unsafe {
+            let dib = Dib::new(4, 4).expect("cannot allocate the test bitmap");
+            let mut st = DcState::default();
+            let mut cache = PenCache::new(1);
+            let pen = cache.get(dib.dc, &mut st, 0xff, 3, cache.stroke_style);
+            st.select_pen(dib.dc, pen as windef::HGDIOBJ);
+
+            cache.get(dib.dc, &mut st, 0xff00, 3, cache.stroke_style);
+            assert_ne!(wingdi::GetCurrentObject(dib.dc, wingdi::OBJ_PEN), pen as windef::HGDIOBJ);
+            // a deleted handle has no object type any more, one still selected would have survived DeleteObject
+            assert_eq!(wingdi::GetObjectType(pen as windef::HGDIOBJ), 0);
+            cache.flush(dib.dc, &mut st);
+        }
+    }
+
+    // Draws on a black DIB and returns the pixels that are not black any more.
+    unsafe fn drawn<F: FnOnce(&mut GdiSurface)>(draw: F) -> Vec<((i32, i32), [u8; 4])> {
+        let dib = Dib::new(SIZE as i32, SIZE as i32).expect("cannot allocate the test bitmap");
+        let mut surface = GdiSurface::new(dib.dc, SIZE as i32, SIZE as i32);
+        surface.clear(Color { r: 0, g: 0, b: 0, a: 255 });
+        draw(&mut surface);
+
+        let mut pixels = Vec::new();
+        for y in 0..SIZE as i32 {
+            for x in 0..SIZE as i32 {
+                let pixel = surface.read_pixel(x, y);
+                if pixel[..3] != [0, 0, 0] {
+                    pixels.push(((x, y), pixel));
+                }
+            }
+        }
+        pixels
+    }
+
+    #[test]
+    fn translucent_shapes_are_composited_and_transparent_ones_skipped() {
+        unsafe {
+            let half = drawn(|s| s.fill_rect(4, 4, 8, 8, 0, Color { r: 255, g: 255, b: 255, a: 128 }));
+            assert_eq!(half.len(), 64);
+            assert!(half.iter().all(|&(_, p)| p[..3].iter().all(|&c| c >= 127 && c <= 129)), "{:?}", half[0]);
+
+            assert_eq!(drawn(|s| s.fill_rect(4, 4, 8, 8, 0, Color { r: 255, g: 255, b: 255, a: 0 })), vec![]);
+            assert_eq!(drawn(|s| s.stroke_polyline(&[Vec2i { x: 4, y: 4 }, Vec2i { x: 40, y: 40 }], 5, Color { r: 255, g: 0, b: 0, a: 0 })), vec![]);
+        }
+    }
+
+    #[test]
+    fn translucent_miter_joins_are_not_cut_off() {
+        // the miter tip lies about 12 pixels right of the vertex at x = 40
+        let points = [Vec2i { x: 8, y: 12 }, Vec2i { x: 40, y: 20 }, Vec2i { x: 8, y: 28 }];
+        unsafe {
+            let opaque: Vec<(i32, i32)> = drawn(|s| s.stroke_polyline(&points, 6, Color { r: 255, g: 255, b: 255, a: 255 })).into_iter().map(|(p, _)| p).collect();
+            let translucent: Vec<(i32, i32)> = drawn(|s| s.stroke_polyline(&points, 6, Color { r: 255, g: 255, b: 255, a: 128 })).into_iter().map(|(p, _)| p).collect();
+            assert!(opaque.iter().any(|&(x, _)| x > 48));
+            assert_eq!(translucent, opaque);
+        }
+    }
+
+    #[test]
+    fn removed_font_ids_stay_stale_after_their_slot_is_reused() {
+        let mut fonts = FontStore::default();
+        let first = fonts.insert(1);
+        assert_eq!(fonts.remove(first), Some(1));
+        assert_eq!(fonts.get(first), None);
+        assert_eq!(fonts.remove(first), None);
+
+        let second = fonts.insert(2);
+        assert_eq!((second.index, second.generation), (first.index, first.generation + 1));
+        assert_eq!(fonts.get(first), None);
+        assert_eq!(fonts.remove(first), None);
+        assert_eq!(fonts.get(second), Some(&2));
+        assert_eq!(GdiFontID::from_raw(second.to_raw()), second);
+        assert_eq!(fonts.iter().collect::<Vec<_>>(), vec![(second, &2)]);
+    }
+
+    #[test]
+    fn font_slots_are_retired_before_their_generation_wraps() {
+        let mut fonts = FontStore::default();
+        let old = fonts.insert(1);
+        fonts.slots[0].generation = u16::max_value() - 1;
+        let last = GdiFontID { index: 0, generation: u16::max_value() - 1 };
+        assert_eq!(fonts.remove(last), Some(1));
+
+        let next = fonts.insert(2);
+        assert_eq!(next.index, 1);
+        assert_eq!(fonts.get(old), None);
+        assert_eq!(fonts.get(GdiFontID { index: 0, generation: u16::max_value() }), None);
+    }
+
+    #[test]
+    fn draws_straight_into_the_backbuffer_when_fast() {
+        unsafe {
+            let memory = Dib::new(SIZE as i32, SIZE as i32).expect("cannot allocate the test bitmap");
+            let mut surface = GdiSurface::new(memory.dc, SIZE as i32, SIZE as i32);
+            nk_gdi_draw(&mut surface, None, Quality::Fast, memory.dc, SIZE as i32, SIZE as i32, Color { r: 0, g: 0, b: 255, a: 255 }, |s| s.fill_rect(8, 8, 16, 16, 0, Color { r: 255, g: 0, b: 0, a: 255 }));
+
+            assert_eq!(surface.read_pixel(4, 4), [0, 0, 255, 255]);
+            assert_eq!(surface.read_pixel(8, 8), [255, 0, 0, 255]);
+            assert_eq!(surface.read_pixel(23, 23), [255, 0, 0, 255]);
+            assert_eq!(surface.read_pixel(24, 24), [0, 0, 255, 255]);
+        }
+    }
+
+    #[test]
+    fn supersampled_frames_are_downsampled_into_the_backbuffer() {
+        unsafe {
+            let memory = Dib::new(SIZE as i32, SIZE as i32).expect("cannot allocate the test bitmap");
+            let enlarged = Dib::new(SIZE as i32 * 2, SIZE as i32 * 2).expect("cannot allocate the test bitmap");
+            let mut surface = GdiSurface::new(enlarged.dc, enlarged.width, enlarged.height);
+            surface.set_scale(2);
+            let quality = Quality::Supersampled { factor: 2, filter: supersample::Filter::Box };
+            nk_gdi_draw(&mut surface, Some(&enlarged), quality, memory.dc, SIZE as i32, SIZE as i32, Color { r: 0, g: 0, b: 255, a: 255 }, |s| s.fill_rect(8, 8, 16, 16, 0, Color { r: 255, g: 0, b: 0, a: 255 }));
+
+            // drawing went to the enlarged DIB at twice the size
+            assert_eq!(surface.read_pixel(16, 16), [255, 0, 0, 255]);
+            assert_eq!(surface.read_pixel(47, 47), [255, 0, 0, 255]);
+            assert_eq!(surface.read_pixel(48, 48), [0, 0, 255, 255]);
+
+            let mut backbuffer = GdiSurface::new(memory.dc, SIZE as i32, SIZE as i32);
+            assert_eq!(backbuffer.read_pixel(4, 4), [0, 0, 255, 255]);
+            assert_eq!(backbuffer.read_pixel(8, 8), [255, 0, 0, 255]);
+            assert_eq!(backbuffer.read_pixel(23, 23), [255, 0, 0, 255]);
+            assert_eq!(backbuffer.read_pixel(24, 24), [0, 0, 255, 255]);
+        }
+    }
+
+    #[test]
+    fn arc_cases_match_software() {
+        assert_eq!(check(&conformance::arc_cases(), CURVE_TOLERANCE), vec![]);
+    }
+
+    #[test]
+    fn round_rect_cases_match_software() {
+        assert_eq!(check(&conformance::round_rect_cases(), CURVE_TOLERANCE), vec![]);
+    }
+
+    #[test]
+    fn stroke_cases_match_software() {
+        assert_eq!(check(&conformance::stroke_cases(), STROKE_TOLERANCE), vec![]);
+    }
+
+    #[test]
+    fn bounds_cases_hold() {
+        unsafe {
+            let dib = Dib::new(SIZE as i32, SIZE as i32).expect("cannot allocate the test bitmap");
+            let mut surface = GdiSurface::new(dib.dc, SIZE as i32, SIZE as i32);
+            assert_eq!(conformance::check_bounds(&mut surface, &conformance::bounds_cases()), vec![]);
+        }
+    }
+}
@@ -0,0 +1,292 @@
+use super::*;
+
+use std::fmt;
+use std::os::windows::ffi::OsStrExt;
+use std::path::Path;
+use winapi::um::winreg;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FontError {
+    /// GDI could not create a font from the descriptor.
+    Creation { name: String },
+    /// The face is not installed (or registered) and GDI picked `actual` instead.
+    Substituted { requested: String, actual: String },
+    /// A font file or in-memory font could not be registered; `source` is the path or `"memory"`.
+    Registration { source: String },
+}
+
+impl fmt::Display for FontError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FontError::Creation { ref name } => write!(f, "cannot create font {}", name),
+            FontError::Substituted { ref requested, ref actual } => write!(f, "font {} is not available, GDI substituted {}", requested, actual),
+            FontError::Registration { ref source } => write!(f, "cannot register fonts from {}", source),
+        }
+    }
+}
+
+/// How glyph edges are smoothed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FontQuality {
+    /// Whatever the system is set to.
+    Default,
+    /// Grayscale antialiasing.
+    Antialiased,
+    /// Subpixel antialiasing.
+    ClearType,
+    /// Aliased edges.
+    NonAntialiased,
+}
+
+impl Default for FontQuality {
+    fn default() -> FontQuality {
+        FontQuality::ClearType
+    }
+}
+
+impl FontQuality {
+    fn to_gdi(self) -> u32 {
+        match self {
+            FontQuality::Default => wingdi::DEFAULT_QUALITY,
+            FontQuality::Antialiased => wingdi::ANTIALIASED_QUALITY,
+            FontQuality::ClearType => wingdi::CLEARTYPE_QUALITY,
+            FontQuality::NonAntialiased => wingdi::NONANTIALIASED_QUALITY,
+        }
+    }
+}
+
+/// What `Drawer::new_font` should create: a face name and pixel height, refined with the builder methods,
+/// e.g. `FontDescriptor::new("Segoe UI", 20).weight(wingdi::FW_BOLD)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontDescriptor {
+    name: String,
+    size: i32,
+    weight: i32,
+    italic: bool,
+    underline: bool,
+    strikeout: bool,
+    charset: u32,
+    pitch_and_family: u32,
+    quality: FontQuality,
+}
+
+impl FontDescriptor {
+    /// A regular weight, upright font with the default charset, pitch and family and ClearType smoothing.
+    pub fn new(name: &str, size: u16) -> FontDescriptor {
+        FontDescriptor {
+            name: name.to_owned(),
+            size: size as i32,
+            weight: wingdi::FW_NORMAL,
+            italic: false,
+            underline: false,
+            strikeout: false,
+            charset: wingdi::DEFAULT_CHARSET,
+            pitch_and_family: wingdi::DEFAULT_PITCH | wingdi::FF_DONTCARE,
+            quality: FontQuality::default(),
+        }
+    }
+
+    /// 100 to 900, as the `FW_*` constants; 400 is regular and 700 bold.
+    pub fn weight(mut self, weight: i32) -> FontDescriptor {
+        self.weight = weight;
+        self
+    }
+
+    pub fn italic(mut self, italic: bool) -> FontDescriptor {
+        self.italic = italic;
+        self
+    }
+
+    pub fn underline(mut self, underline: bool) -> FontDescriptor {
+        self.underline = underline;
+        self
+    }
+
+    pub fn strikeout(mut self, strikeout: bool) -> FontDescriptor {
+        self.strikeout = strikeout;
+        self
+    }
+
+    /// One of the `*_CHARSET` constants.
+    pub fn charset(mut self, charset: u32) -> FontDescriptor {
+        self.charset = charset;
+        self
+    }
+
+    /// A `*_PITCH` constant or-ed with an `FF_*` family.
+    pub fn pitch_and_family(mut self, pitch_and_family: u32) -> FontDescriptor {
+        self.pitch_and_family = pitch_and_family;
+        self
+    }
+
+    pub fn quality(mut self, quality: FontQuality) -> FontDescriptor {
+        self.quality = quality;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn size(&self) -> u16 {
+        self.size as u16
+    }
+
+    pub(super) unsafe fn create(&self) -> Result<windef::HFONT, FontError> {
+        let name: Vec<u16> = self.name.encode_utf16().chain(Some(0)).collect();
+
+        let handle = wingdi::CreateFontW(
+            self.size,
+            0,
+            0,
+            0,
+            self.weight,
+            self.italic as u32,
+            self.underline as u32,
+            self.strikeout as u32,
+            self.charset,
+            wingdi::OUT_DEFAULT_PRECIS,
+            wingdi::CLIP_DEFAULT_PRECIS,
+            self.quality.to_gdi(),
+            self.pitch_and_family,
+            name.as_ptr(),
+        );
+        if handle.is_null() {
+            Err(FontError::Creation { name: self.name.clone() })
+        } else {
+            Ok(handle)
+        }
+    }
+
+    // Fails when GDI substituted another face for the one asked for. An empty name leaves the choice to GDI.
+    // Names are compared the way GDI keeps them, cut to `LF_FACESIZE - 1` units. A face reported under another name still
+    // passes when the name is an installed family, as localized names are, or an alias of the selected face.
+    pub(super) unsafe fn verify(&self, dc: windef::HDC) -> Result<(), FontError> {
+        if self.name.is_empty() {
+            return Ok(());
+        }
+
+        let requested = face_units(&self.name);
+        let actual = face_name(dc);
+        let actual_units: Vec<u16> = actual.encode_utf16().collect();
+        if same_face(&requested, &actual_units) || family_exists(dc, &requested, self.charset) || substitute(&requested).map_or(false, |face| same_face(&face, &actual_units)) {
+            Ok(())
+        } else {
+            Err(FontError::Substituted { requested: self.name.clone(), actual: actual })
+        }
+    }
+}
+
+unsafe fn face_name(dc: windef::HDC) -> String {
+    let mut name = [0u16; wingdi::LF_FACESIZE];
+    let len = wingdi::GetTextFaceW(dc, name.len() as i32, name.as_mut_ptr());
+    // the count includes the terminating null
+    String::from_utf16_lossy(&name[..(len.max(1) as usize - 1).min(name.len())])
+}
+
+// The face name as GDI stores it in a `LOGFONTW`.
+fn face_units(name: &str) -> Vec<u16> {
+    name.encode_utf16().take(wingdi::LF_FACESIZE - 1).collect()
+}
+
+fn same_face(a: &[u16], b: &[u16]) -> bool {
+    String::from_utf16_lossy(a).to_lowercase() == String::from_utf16_lossy(b).to_lowercase()
+}
+
+unsafe fn family_exists(dc: windef::HDC, face: &[u16], charset: u32) -> bool {
+    let mut logfont: wingdi::LOGFONTW = mem::zeroed();
+    logfont.lfCharSet = charset as u8;
+    logfont.lfFaceName[..face.len()].copy_from_slice(face);
+
+    let mut found = false;
+    wingdi::EnumFontFamiliesExW(dc, &mut logfont, Some(family_found), &mut found as *mut bool as minwindef::LPARAM, 0);
+    found
+}
+
+unsafe extern "system" fn family_found(_: *const wingdi::LOGFONTW, _: *const wingdi::TEXTMETRICW, _: minwindef::DWORD, found: minwindef::LPARAM) -> i32 {
+    *(found as *mut bool) = true;
+    // one match is enough
+    0
+}
+
+// The face an alias such as "MS Shell Dlg" is mapped to, from the system's FontSubstitutes list.
+unsafe fn substitute(face: &[u16]) -> Option<Vec<u16>> {
+    let key: Vec<u16> = "SOFTWARE\\Microsoft\\Windows NT\\CurrentVersion\\FontSubstitutes".encode_utf16().chain(Some(0)).collect();
+    let value: Vec<u16> = face.iter().cloned().chain(Some(0)).collect();
+    let mut data = [0u16; 256];
+    let mut size = (data.len() * 2) as minwindef::DWORD;
+    if winreg::RegGetValueW(winreg::HKEY_LOCAL_MACHINE, key.as_ptr(), value.as_ptr(), winreg::RRF_RT_REG_SZ, ptr::null_mut(), data.as_mut_ptr() as *mut c_void, &mut size) != 0 {
+        return None;
+    }
+    // "Face" or "Face,charset"
+    let len = data.iter().position(|&c| c == 0 || c == ',' as u16).unwrap_or(data.len());
+    Some(data[..len].to_vec())
+}
+
+// Fonts registered for this process only, removed again on drop.
+#[derive(Default)]
+pub(super) struct PrivateFonts {
+    files: Vec<Vec<u16>>,
+    memory: Vec<ntdef::HANDLE>,
+}
+
+impl PrivateFonts {
+    pub(super) fn add_file(&mut self, path: &Path) -> Result<(), FontError> {
+        let name: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+        if unsafe { wingdi::AddFontResourceExW(name.as_ptr(), wingdi::FR_PRIVATE, ptr::null_mut()) } == 0 {
+            return Err(FontError::Registration { source: path.display().to_string() });
+        }
+        self.files.push(name);
+        Ok(())
+    }
+
+    // GDI copies the data, so it need not outlive the call.
+    pub(super) fn add_memory(&mut self, data: &[u8]) -> Result<(), FontError> {
+        let mut count = 0;
+        let handle = unsafe { wingdi::AddFontMemResourceEx(data.as_ptr() as *mut c_void, data.len() as u32, ptr::null_mut(), &mut count) };
+        if handle.is_null() || count == 0 {
+            return Err(FontError::Registration { source: "memory".to_owned() });
+        }
+        self.memory.push(handle);
+        Ok(())
+    }
+}
+
+impl Drop for PrivateFonts {
+    fn drop(&mut self) {
+        unsafe {
+            for name in &self.files {
+                wingdi::RemoveFontResourceExW(name.as_ptr(), wingdi::FR_PRIVATE, ptr::null_mut());
+            }
+            for &handle in &self.memory {
+                wingdi::RemoveFontMemResourceEx(handle);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn face_names_are_cut_like_gdi_cuts_them() {
+        let long = "A Font Family Name Longer Than GDI Keeps";
+        assert_eq!(face_units(long).len(), wingdi::LF_FACESIZE - 1);
+        assert!(same_face(&face_units(long), &long[..31].encode_utf16().collect::<Vec<_>>()));
+        assert!(same_face(&face_units("arial"), &face_units("ARIAL")));
+        assert!(!same_face(&face_units("Arial"), &face_units("Arial Black")));
+    }
+
+    #[test]
+    fn installed_faces_pass_and_missing_ones_are_substituted() {
+        unsafe {
+            assert!(GdiFont::new(&FontDescriptor::new("Arial", 12)).is_ok());
+            assert!(GdiFont::new(&FontDescriptor::new("arial", 12)).is_ok());
+            match GdiFont::new(&FontDescriptor::new("No Such Face Anywhere", 12)) {
+                Err(FontError::Substituted { ref requested, .. }) => assert_eq!(requested, "No Such Face Anywhere"),
+                _ => panic!("a missing face was not reported"),
+            }
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use super::*;
+
+use crate::raster::{self, Target, Texture, TextureFormat, Vertex};
+use std::collections::HashMap;
+use std::{mem, ptr, slice};
+
+const ATLAS_WIDTH: i32 = 512;
+const SEGMENTS: u32 = 22;
+
+// Glyphs of one font, rendered on demand with `GetGlyphOutlineW` for the vertex path.
+// The UVs handed to nuklear are in atlas pixels, which is what `raster` expects of a non-normalized texture.
+pub(super) struct GlyphAtlas {
+    height: i32,
+    pixels: Vec<u8>,
+    cursor: (i32, i32),
+    row: i32,
+    glyphs: HashMap<u32, nksys::nk_user_font_glyph>,
+}
+
+impl GlyphAtlas {
+    pub(super) fn new() -> GlyphAtlas {
+        GlyphAtlas {
+            height: 0,
+            pixels: Vec::new(),
+            cursor: (0, 0),
+            row: 0,
+            glyphs: HashMap::new(),
+        }
+    }
+
+    // `dc` has the font selected; `ascent` places the glyph relative to the top of the line.
+    unsafe fn glyph(&mut self, dc: windef::HDC, ascent: i32, codepoint: u32) -> nksys::nk_user_font_glyph {
+        if let Some(glyph) = self.glyphs.get(&codepoint) {
+            return *glyph;
+        }
+
+        let one = wingdi::FIXED { fract: 0, value: 1 };
+        let zero = wingdi::FIXED { fract: 0, value: 0 };
+        let identity = wingdi::MAT2 { eM11: one, eM12: zero, eM21: zero, eM22: one };
+        let mut metrics: wingdi::GLYPHMETRICS = mem::zeroed();
+        let mut glyph: nksys::nk_user_font_glyph = mem::zeroed();
+
+        let size = wingdi::GetGlyphOutlineW(dc, codepoint, wingdi::GGO_GRAY8_BITMAP, &mut metrics, 0, ptr::null_mut(), &identity);
+        if size != wingdi::GDI_ERROR {
+            glyph.xadvance = metrics.gmCellIncX as f32;
+        }
+        // whitespace has no bitmap, only an advance
+        if size != wingdi::GDI_ERROR && size > 0 {
+            let mut bits = vec![0u8; size as usize];
+            wingdi::GetGlyphOutlineW(dc, codepoint, wingdi::GGO_GRAY8_BITMAP, &mut metrics, size, bits.as_mut_ptr() as *mut c_void, &identity);
+
+            let (w, h) = (metrics.gmBlackBoxX as i32, metrics.gmBlackBoxY as i32);
+            let pitch = ((w + 3) & !3) as usize;
+            let (x, y) = self.allocate(w, h);
+            for row in 0..h as usize {
+                for col in 0..w as usize {
+                    // GGO_GRAY8_BITMAP has 65 levels
+                    let coverage = (bits[row * pitch + col] as u32 * 255 / 64).min(255) as u8;
+                    self.pixels[(y as usize + row) * ATLAS_WIDTH as usize + x as usize + col] = coverage;
+                }
+            }
+
+            glyph.uv[0] = nksys::nk_vec2 { x: x as f32, y: y as f32 };
+            glyph.uv[1] = nksys::nk_vec2 { x: (x + w) as f32, y: (y + h) as f32 };
+            glyph.offset = nksys::nk_vec2 {
+                x: metrics.gmptGlyphOrigin.x as f32,
+                y: (ascent - metrics.gmptGlyphOrigin.y) as f32,
+            };
+            glyph.width = w as f32;
+            glyph.height = h as f32;
+        }
+
+        self.glyphs.insert(codepoint, glyph);
+        glyph
+    }
+
+    // Shelf packing with a one pixel gap; the atlas grows downwards.
+    fn allocate(&mut self, w: i32, h: i32) -> (i32, i32) {
+        if self.cursor.0 + w + 1 > ATLAS_WIDTH {
+            self.cursor = (0, self.cursor.1 + self.row);
+            self.row = 0;
+        }
+
+        let (x, y) = self.cursor;
+        self.cursor.0 += w + 1;
+        self.row = self.row.max(h + 1);
+        if y + h > self.height {
+            self.height = (y + h + 1).max(self.height * 2);
+            self.pixels.resize((ATLAS_WIDTH * self.height) as usize, 0);
+        }
+        (x, y)
+    }
+
+    fn texture(&self) -> Texture {
+        Texture {
+            width: ATLAS_WIDTH as u32,
+            height: self.height as u32,
+            format: TextureFormat::Alpha,
+            pixels: &self.pixels,
+            normalized: false,
+        }
+    }
+}
+
+pub(super) unsafe extern "C" fn nk_gdifont_query_glyph(handle: nksys::nk_handle, _: f32, glyph: *mut nksys::nk_user_font_glyph, codepoint: nksys::nk_rune, _: nksys::nk_rune) {
+    let font = handle.ptr as *mut GdiFont;
+    if font.is_null() || glyph.is_null() {
+        return;
+    }
+
+    *glyph = (*font).atlas.glyph((*font).dc, (*font).ascent, codepoint);
+}
+
+// Buffers of the `RenderMode::Vertices` path, kept between frames.
+pub(super) struct VertexRenderer {
+    layout: DrawVertexLayoutElements,
+    commands: Buffer,
+    // boxed so the address handed to `commands` stays put; dropped after it
+    _allocator: Box<Allocator>,
+    vertices: Vec<Vertex>,
+    elements: Vec<u16>,
+    vertex_count: usize,
+    element_count: usize,
+    pixels: Vec<u8>,
+    images: HashMap<usize, (u32, u32, Vec<u8>)>,
+}
+
+impl VertexRenderer {
+    pub(super) fn new() -> VertexRenderer {
+        let mut allocator = Box::new(Allocator::new_vec());
+        let commands = Buffer::with_size(&mut allocator, 4096);
+
+        VertexRenderer {
+            layout: raster::vertex_layout(),
+            commands: commands,
+            _allocator: allocator,
+            vertices: vec![Vertex::default(); 4096],
+            elements: vec![0; 8192],
+            vertex_count: 0,
+            element_count: 0,
+            pixels: Vec::new(),
+            images: HashMap::new(),
+        }
+    }
+
+    /// Runs `nk_convert` on the queued commands, growing the vertex and element buffers until everything fits.
+    /// Font glyphs are added to the atlases as nuklear queries them.
+    pub(super) fn convert(&mut self, ctx: &mut Context) {
+        let mut config = ConvertConfig::default();
+        config.set_null(DrawNullTexture::default());
+        config.set_circle_segment_count(SEGMENTS);
+        config.set_curve_segment_count(SEGMENTS);
+        config.set_arc_segment_count(SEGMENTS);
+        config.set_global_alpha(1.0);
+        config.set_shape_aa(AntiAliasing::On);
+        config.set_line_aa(AntiAliasing::On);
+        config.set_vertex_layout(&self.layout);
+        config.set_vertex_size(mem::size_of::<Vertex>());
+
+        loop {
+            self.commands.clear();
+            let (vertex_bytes, element_bytes) = unsafe {
+                let mut vertices = Buffer::with_fixed(slice::from_raw_parts_mut(self.vertices.as_mut_ptr() as *mut u8, self.vertices.len() * mem::size_of::<Vertex>()));
+                let mut elements = Buffer::with_fixed(slice::from_raw_parts_mut(self.elements.as_mut_ptr() as *mut u8, self.elements.len() * mem::size_of::<u16>()));
+                ctx.convert(&mut self.commands, &mut vertices, &mut elements, &config);
+                (needed(&vertices), needed(&elements))
+            };
+
+            self.vertex_count = vertex_bytes / mem::size_of::<Vertex>();
+            self.element_count = element_bytes / mem::size_of::<u16>();
+            if self.vertex_count <= self.vertices.len() && self.element_count <= self.elements.len() {
+                return;
+            }
+
+            let (vertices, elements) = (self.vertices.len().max(self.vertex_count) * 2, self.elements.len().max(self.element_count) * 2);
+            self.vertices.resize(vertices, Vertex::default());
+            self.elements.resize(elements, 0);
+        }
+    }
+
+    /// Rasterizes the last conversion and copies it onto `dc`.
+    pub(super) unsafe fn draw(&mut self, ctx: &mut Context, fonts: &FontStore, dc: windef::HDC, width: i32, height: i32, clear: Color) {
+        self.pixels.resize((width * height * 4) as usize, 0);
+        let mut target = Target {
+            pixels: &mut self.pixels,
+            width: width,
+            height: height,
+        };
+        target.clear(clear);
+
+        let vertices = &self.vertices[..self.vertex_count];
+        let mut offset = 0;
+        for cmd in ctx.draw_command_iterator(&self.commands) {
+            let count = cmd.elem_count() as usize;
+            if count == 0 {
+                continue;
+            }
+
+            let r = cmd.clip_rect();
+            let clip = (r.x.floor() as i32, r.y.floor() as i32, (r.x + r.w).ceil() as i32, (r.y + r.h).ceil() as i32);
+            let indices = &self.elements[offset.min(self.element_count)..(offset + count).min(self.element_count)];
+            let mut texture = cmd.texture();
+            let key = texture.ptr().map_or(0, |p| p as usize);
+
+            if key == 0 {
+                target.draw_triangles(vertices, indices, clip, None);
+            } else if let Some((_, font)) = fonts.iter().find(|&(_, f)| &**f as *const GdiFont as usize == key) {
+                target.draw_triangles(vertices, indices, clip, Some(&font.atlas.texture()));
+            } else {
+                let &mut (w, h, ref pixels) = self.images.entry(key).or_insert_with(|| load_bitmap(dc, key as windef::HBITMAP));
+                let image = Texture {
+                    width: w,
+                    height: h,
+                    format: TextureFormat::Rgba,
+                    pixels: pixels,
+                    normalized: true,
+                };
+                target.draw_triangles(vertices, indices, clip, Some(&image));
+            }
+            offset += count;
+        }
+
+        for p in self.pixels.chunks_mut(4) {
+            p.swap(0, 2);
+        }
+        let bminfo = nk_gdi_dib_info(width, height);
+        wingdi::SetDIBitsToDevice(dc, 0, 0, width as u32, height as u32, 0, 0, 0, height as u32, self.pixels.as_ptr() as *const c_void, &bminfo, wingdi::DIB_RGB_COLORS);
+    }
+
+    /// Forgets the pixels read from image bitmaps, so changed images are picked up again.
+    pub(super) fn clear_image_cache(&mut self) {
+        self.images.clear();
+    }
+}
+
+// Bytes `nk_convert` wanted to write, including what did not fit.
+fn needed(buffer: &Buffer) -> usize {
+    unsafe { (*(buffer as *const _ as *const nksys::nk_buffer)).needed as usize }
+}
+
+// Straight RGBA copy of an image bitmap, top row first.
+unsafe fn load_bitmap(dc: windef::HDC, bitmap: windef::HBITMAP) -> (u32, u32, Vec<u8>) {
+    let mut info: wingdi::BITMAP = mem::zeroed();
+    if wingdi::GetObjectW(bitmap as *mut c_void, mem::size_of::<wingdi::BITMAP>() as i32, &mut info as *mut _ as *mut c_void) == 0 {
+        return (0, 0, Vec::new());
+    }
+
+    let (w, h) = (info.bmWidth, info.bmHeight.abs());
+    let mut bminfo = nk_gdi_dib_info(w, h);
+    let mut pixels = vec![0u8; (w * h * 4) as usize];
+    wingdi::GetDIBits(dc, bitmap, 0, h as u32, pixels.as_mut_ptr() as *mut c_void, &mut bminfo, wingdi::DIB_RGB_COLORS);
+    for p in pixels.chunks_mut(4) {
+        p.swap(0, 2);
+    }
+    (w as u32, h as u32, pixels)
+}
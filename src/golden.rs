@@ -0,0 +1,247 @@
+use crate::software::SoftwareSurface;
+use image::{Rgba, RgbaImage};
+use nuklear::*;
+use std::path::{Path, PathBuf};
+use std::{fmt, io};
+
+#[derive(Debug)]
+pub enum GoldenError {
+    Io(io::Error),
+    Image(image::ImageError),
+    /// No reference image exists yet; the rendered frame was written to `actual`.
+    Missing { reference: PathBuf, actual: PathBuf },
+    /// `pixels` pixels differ by more than the tolerance; see `actual` and `diff`.
+    Mismatch { reference: PathBuf, actual: PathBuf, diff: PathBuf, pixels: usize },
+}
+
+impl fmt::Display for GoldenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GoldenError::Io(ref e) => write!(f, "I/O error: {}", e),
+            GoldenError::Image(ref e) => write!(f, "image error: {}", e),
+            GoldenError::Missing { ref reference, ref actual } => write!(f, "missing reference {}, rendered frame saved as {}", reference.display(), actual.display()),
+            GoldenError::Mismatch { ref reference, ref diff, pixels, .. } => write!(f, "{} pixels differ from {}, see {}", pixels, reference.display(), diff.display()),
+        }
+    }
+}
+
+impl From<io::Error> for GoldenError {
+    fn from(e: io::Error) -> GoldenError {
+        GoldenError::Io(e)
+    }
+}
+
+impl From<image::ImageError> for GoldenError {
+    fn from(e: image::ImageError) -> GoldenError {
+        GoldenError::Image(e)
+    }
+}
+
+/// Renders scripted frames headlessly and compares them with PNG references stored as `<dir>/<name>_<frame>.png`.
+pub struct GoldenHarness {
+    dir: PathBuf,
+    surface: SoftwareSurface,
+    clear: Color,
+    tolerance: u8,
+    update: bool,
+}
+
+impl GoldenHarness {
+    pub fn new<P: AsRef<Path>>(dir: P, width: u16, height: u16) -> GoldenHarness {
+        GoldenHarness {
+            dir: dir.as_ref().to_path_buf(),
+            surface: SoftwareSurface::new(width, height),
+            clear: Color { r: 0, g: 0, b: 0, a: 255 },
+            tolerance: 0,
+            update: false,
+        }
+    }
+
+    /// Largest per-channel difference still counted as a match.
+    pub fn set_tolerance(&mut self, tolerance: u8) {
+        self.tolerance = tolerance;
+    }
+
+    pub fn set_clear_color(&mut self, clear: Color) {
+        self.clear = clear;
+    }
+
+    /// When set, references are (re)written from the rendered frames instead of compared.
+    pub fn set_update(&mut self, update: bool) {
+        self.update = update;
+    }
+
+    /// Gives access to the surface, e.g. to register images or a glyph source.
+    pub fn surface_mut(&mut self) -> &mut SoftwareSurface {
+        &mut self.surface
+    }
+
+    /// Runs `script` for `frames` frames, rendering and checking each of them. Stops at the first failing frame.
+    pub fn run<F: FnMut(&mut Context, usize)>(&mut self, ctx: &mut Context, name: &str, frames: usize, mut script: F) -> Result<(), GoldenError> {
+        for frame in 0..frames {
+            script(ctx, frame);
+            self.surface.render(ctx, self.clear);
+            self.check(&format!("{}_{}", name, frame))?;
+        }
+        Ok(())
+    }
+
+    /// Compares the current surface contents with the reference called `name`.
+    pub fn check(&self, name: &str) -> Result<(), GoldenError> {
+        let reference = self.dir.join(format!("{}.png", name));
+        let actual_path = self.dir.join(format!("{}.actual.png", name));
+        let actual = self.surface.to_image();
+
+        if self.update {
+            actual.save(&reference)?;
+            return Ok(());
+        }
+        if !reference.exists() {
+            actual.save(&actual_path)?;
+            return Err(GoldenError::Missing { reference: reference, actual: actual_path });
+        }
+
+        let expected = image::open(&reference)?.to_rgba();
+        let (diff, pixels) = compare(&expected, &actual, self.tolerance);
+        if pixels == 0 {
+            return Ok(());
+        }
+
+        let diff_path = self.dir.join(format!("{}.diff.png", name));
+        actual.save(&actual_path)?;
+        diff.save(&diff_path)?;
+        Err(GoldenError::Mismatch {
+            reference: reference,
+            actual: actual_path,
+            diff: diff_path,
+            pixels: pixels,
+        })
+    }
+}
+
+/// Returns a diff image (mismatches in red over a dimmed reference) and the number of mismatching pixels.
+pub fn compare(expected: &RgbaImage, actual: &RgbaImage, tolerance: u8) -> (RgbaImage, usize) {
+    let (w, h) = (expected.width().max(actual.width()), expected.height().max(actual.height()));
+    let mut diff = RgbaImage::new(w, h);
+    let mut pixels = 0;
+
+    for y in 0..h {
+        for x in 0..w {
+            let e = if x < expected.width() && y < expected.height() { Some(expected.get_pixel(x, y)) } else { None };
+            let a = if x < actual.width() && y < actual.height() { Some(actual.get_pixel(x, y)) } else { None };
+
+            let matches = match (e, a) {
+                (Some(e), Some(a)) => e.data.iter().zip(a.data.iter()).all(|(e, a)| (*e as i16 - *a as i16).abs() <= tolerance as i16),
+                _ => false,
+            };
+
+            if matches {
+                let e = e.unwrap();
+                let luma = ((e[0] as u32 + e[1] as u32 + e[2] as u32) / 3 / 4) as u8;
+                diff.put_pixel(x, y, Rgba([luma, luma, luma, 255]));
+            } else {
+                pixels += 1;
+                diff.put_pixel(x, y, Rgba([255, 0, 0, 255]));
+            }
+        }
+    }
+    (diff, pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::surface::Surface;
+    use nuklear::nuklear_sys as nksys;
+    use std::{env, fs, mem, process};
+
+    const GRAY: Color = Color { r: 100, g: 100, b: 100, a: 255 };
+    const WHITE: Color = Color { r: 255, g: 255, b: 255, a: 255 };
+
+    // A fresh directory per test, so tests running in parallel do not see each other's references.
+    fn harness(test: &str) -> GoldenHarness {
+        let dir = env::temp_dir().join(format!("nuklear-golden-{}-{}", process::id(), test));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut harness = GoldenHarness::new(&dir, 16, 16);
+        harness.surface_mut().clear(Color { r: 0, g: 0, b: 0, a: 255 });
+        harness
+    }
+
+    fn update(harness: &mut GoldenHarness, name: &str) {
+        harness.set_update(true);
+        harness.check(name).unwrap();
+        harness.set_update(false);
+    }
+
+    #[test]
+    fn a_missing_reference_saves_the_rendered_frame() {
+        let harness = harness("missing");
+        match harness.check("frame") {
+            Err(GoldenError::Missing { ref reference, ref actual }) => {
+                assert!(!reference.exists());
+                assert!(actual.exists());
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn an_updated_reference_matches_and_a_change_does_not() {
+        let mut harness = harness("mismatch");
+        harness.surface_mut().fill_rect(2, 2, 4, 4, 0, WHITE);
+        update(&mut harness, "frame");
+        assert!(harness.check("frame").is_ok());
+
+        harness.surface_mut().fill_rect(6, 2, 2, 4, 0, WHITE);
+        match harness.check("frame") {
+            Err(GoldenError::Mismatch { pixels, ref diff, ref actual, .. }) => {
+                assert_eq!(pixels, 8);
+                assert!(diff.exists() && actual.exists());
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn differences_within_the_tolerance_match() {
+        let mut harness = harness("tolerance");
+        harness.surface_mut().fill_rect(0, 0, 16, 16, 0, GRAY);
+        update(&mut harness, "frame");
+
+        harness.surface_mut().fill_rect(0, 0, 16, 16, 0, Color { r: 110, ..GRAY });
+        assert!(harness.check("frame").is_err());
+        harness.set_tolerance(10);
+        assert!(harness.check("frame").is_ok());
+    }
+
+    #[test]
+    fn changed_text_is_a_mismatch() {
+        let mut font: nksys::nk_user_font = unsafe { mem::zeroed() };
+        font.height = 8.0;
+        let font = unsafe { &*(&font as *const nksys::nk_user_font as *const UserFont) };
+        let transparent = Color { r: 0, g: 0, b: 0, a: 0 };
+
+        let mut harness = harness("text");
+        harness.surface_mut().draw_text(0, 0, 16, 8, b"ab", font, transparent, WHITE);
+        update(&mut harness, "frame");
+
+        harness.surface_mut().clear(Color { r: 0, g: 0, b: 0, a: 255 });
+        harness.surface_mut().draw_text(0, 0, 16, 8, b"ac", font, transparent, WHITE);
+        match harness.check("frame") {
+            Err(GoldenError::Mismatch { pixels, .. }) => assert!(pixels > 0),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn compare_counts_pixels_outside_the_smaller_image() {
+        let expected = RgbaImage::from_pixel(4, 4, Rgba([1, 2, 3, 255]));
+        let actual = RgbaImage::from_pixel(4, 5, Rgba([1, 2, 3, 255]));
+        let (diff, pixels) = compare(&expected, &actual, 0);
+        assert_eq!(pixels, 4);
+        assert_eq!(diff.dimensions(), (4, 5));
+        assert_eq!(*diff.get_pixel(0, 4), Rgba([255, 0, 0, 255]));
+    }
+}
@@ -0,0 +1,265 @@
+use nuklear::*;
+use std::mem;
+
+/// Vertex written by `nk_convert` with the layout from `vertex_layout`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Vertex {
+    pub position: [f32; 2],
+    pub uv: [f32; 2],
+    pub color: [u8; 4],
+}
+
+/// Layout describing `Vertex` to `ConvertConfig::set_vertex_layout`.
+pub fn vertex_layout() -> DrawVertexLayoutElements {
+    DrawVertexLayoutElements::new(&[
+        (DrawVertexLayoutAttribute::Position, DrawVertexLayoutFormat::Float, 0),
+        (DrawVertexLayoutAttribute::TexCoord, DrawVertexLayoutFormat::Float, mem::size_of::<[f32; 2]>() as u32),
+        (DrawVertexLayoutAttribute::Color, DrawVertexLayoutFormat::R8G8B8A8, mem::size_of::<[f32; 4]>() as u32),
+        (DrawVertexLayoutAttribute::AttributeCount, DrawVertexLayoutFormat::Count, 0),
+    ])
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextureFormat {
+    /// One coverage byte per texel, white.
+    Alpha,
+    /// Straight RGBA.
+    Rgba,
+}
+
+pub struct Texture<'a> {
+    pub width: u32,
+    pub height: u32,
+    pub format: TextureFormat,
+    pub pixels: &'a [u8],
+    /// UVs are in `0..1` rather than in texels.
+    pub normalized: bool,
+}
+
+impl<'a> Texture<'a> {
+    fn sample(&self, u: f32, v: f32) -> [u8; 4] {
+        let (u, v) = if self.normalized { (u * self.width as f32, v * self.height as f32) } else { (u, v) };
+        if self.width == 0 || self.height == 0 {
+            return [0; 4];
+        }
+
+        let x = (u.floor().max(0.0) as u32).min(self.width - 1);
+        let y = (v.floor().max(0.0) as u32).min(self.height - 1);
+        let i = (y * self.width + x) as usize;
+        match self.format {
+            TextureFormat::Alpha => [255, 255, 255, self.pixels[i]],
+            TextureFormat::Rgba => [self.pixels[i * 4], self.pixels[i * 4 + 1], self.pixels[i * 4 + 2], self.pixels[i * 4 + 3]],
+        }
+    }
+}
+
+/// An RGBA buffer that triangles are blended onto.
+pub struct Target<'a> {
+    pub pixels: &'a mut [u8],
+    pub width: i32,
+    pub height: i32,
+}
+
+impl<'a> Target<'a> {
+    pub fn clear(&mut self, col: Color) {
+        for p in self.pixels.chunks_mut(4) {
+            p.copy_from_slice(&[col.r, col.g, col.b, 255]);
+        }
+    }
+
+    /// Fills the indexed triangles, interpolating color and UV, and blends them over the target.
+    /// Pixels are sampled at their centers with a top-left fill rule, so triangles sharing an edge never overlap.
+    /// `clip` is `(left, top, right, bottom)`; no texture samples as opaque white.
+    pub fn draw_triangles(&mut self, vertices: &[Vertex], indices: &[u16], clip: (i32, i32, i32, i32), texture: Option<&Texture>) {
+        let clip = (clip.0.max(0), clip.1.max(0), clip.2.min(self.width), clip.3.min(self.height));
+        for tri in indices.chunks(3) {
+            if tri.len() == 3 {
+                self.triangle([vertices[tri[0] as usize], vertices[tri[1] as usize], vertices[tri[2] as usize]], clip, texture);
+            }
+        }
+    }
+
+    fn triangle(&mut self, mut v: [Vertex; 3], clip: (i32, i32, i32, i32), texture: Option<&Texture>) {
+        let mut area = edge(v[0].position, v[1].position, v[2].position);
+        if area < 0.0 {
+            v.swap(1, 2);
+            area = -area;
+        }
+        if area <= 0.0 {
+            return;
+        }
+
+        let xs = [v[0].position[0], v[1].position[0], v[2].position[0]];
+        let ys = [v[0].position[1], v[1].position[1], v[2].position[1]];
+        let x0 = (xs.iter().cloned().fold(f32::MAX, f32::min).floor() as i32).max(clip.0);
+        let y0 = (ys.iter().cloned().fold(f32::MAX, f32::min).floor() as i32).max(clip.1);
+        let x1 = (xs.iter().cloned().fold(f32::MIN, f32::max).ceil() as i32).min(clip.2);
+        let y1 = (ys.iter().cloned().fold(f32::MIN, f32::max).ceil() as i32).min(clip.3);
+
+        // weight of the vertex opposite each edge
+        let edges = [(1, 2), (2, 0), (0, 1)];
+        let top_left = [is_top_left(v[1].position, v[2].position), is_top_left(v[2].position, v[0].position), is_top_left(v[0].position, v[1].position)];
+
+        for py in y0..y1 {
+            for px in x0..x1 {
+                let p = [px as f32 + 0.5, py as f32 + 0.5];
+                let mut w = [0f32; 3];
+                let mut inside = true;
+                for (k, &(a, b)) in edges.iter().enumerate() {
+                    w[k] = edge(v[a].position, v[b].position, p);
+                    if w[k] < 0.0 || (w[k] <= 0.0 && !top_left[k]) {
+                        inside = false;
+                        break;
+                    }
+                }
+                if !inside {
+                    continue;
+                }
+
+                let (l0, l1, l2) = (w[0] / area, w[1] / area, w[2] / area);
+                let lerp = |c: usize| v[0].color[c] as f32 * l0 + v[1].color[c] as f32 * l1 + v[2].color[c] as f32 * l2;
+                let mut col = [lerp(0), lerp(1), lerp(2), lerp(3)];
+                if let Some(texture) = texture {
+                    let u = v[0].uv[0] * l0 + v[1].uv[0] * l1 + v[2].uv[0] * l2;
+                    let t = v[0].uv[1] * l0 + v[1].uv[1] * l1 + v[2].uv[1] * l2;
+                    let texel = texture.sample(u, t);
+                    for (c, s) in col.iter_mut().zip(&texel) {
+                        *c = *c * *s as f32 / 255.0;
+                    }
+                }
+                self.blend(px, py, col);
+            }
+        }
+    }
+
+    fn blend(&mut self, x: i32, y: i32, col: [f32; 4]) {
+        let a = col[3] / 255.0;
+        if a <= 0.0 {
+            return;
+        }
+
+        let i = ((y * self.width + x) * 4) as usize;
+        for (c, s) in col.iter().take(3).enumerate() {
+            let d = self.pixels[i + c] as f32;
+            self.pixels[i + c] = (s * a + d * (1.0 - a)).round().max(0.0).min(255.0) as u8;
+        }
+        self.pixels[i + 3] = 255;
+    }
+}
+
+// Twice the signed area of (a, b, p); positive when p is on the inner side of a clockwise (y down) triangle.
+fn edge(a: [f32; 2], b: [f32; 2], p: [f32; 2]) -> f32 {
+    (b[0] - a[0]) * (p[1] - a[1]) - (b[1] - a[1]) * (p[0] - a[0])
+}
+
+fn is_top_left(a: [f32; 2], b: [f32; 2]) -> bool {
+    let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+    dy < 0.0 || (dy <= 0.0 && dx > 0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: i32 = 8;
+    const EVERYWHERE: (i32, i32, i32, i32) = (0, 0, SIZE, SIZE);
+
+    fn vertex(x: f32, y: f32, u: f32, v: f32, color: [u8; 4]) -> Vertex {
+        Vertex { position: [x, y], uv: [u, v], color: color }
+    }
+
+    // (x, y) of every pixel that isn't black, row by row
+    fn drawn(pixels: &[u8]) -> Vec<(i32, i32)> {
+        (0..SIZE * SIZE).filter(|&i| pixels[i as usize * 4..i as usize * 4 + 3] != [0, 0, 0]).map(|i| (i % SIZE, i / SIZE)).collect()
+    }
+
+    fn draw(vertices: &[Vertex], indices: &[u16], clip: (i32, i32, i32, i32), texture: Option<&Texture>) -> Vec<u8> {
+        let mut pixels = vec![0u8; (SIZE * SIZE * 4) as usize];
+        Target { pixels: &mut pixels, width: SIZE, height: SIZE }.draw_triangles(vertices, indices, clip, texture);
+        pixels
+    }
+
+    #[test]
+    fn shared_edges_cover_each_pixel_once() {
+        // a fan around the center whose edges run through pixel centers, in both windings; half-transparent white shows overlaps as 192
+        let half = [255, 255, 255, 128];
+        let vertices = [vertex(3.5, 3.5, 0.0, 0.0, half), vertex(0.5, 0.5, 0.0, 0.0, half), vertex(6.5, 0.5, 0.0, 0.0, half), vertex(6.5, 6.5, 0.0, 0.0, half), vertex(0.5, 6.5, 0.0, 0.0, half)];
+        let pixels = draw(&vertices, &[0, 1, 2, 0, 3, 2, 0, 3, 4, 0, 1, 4], EVERYWHERE, None);
+
+        let expected: Vec<(i32, i32)> = (0..6).flat_map(|y| (0..6).map(move |x| (x, y))).collect();
+        assert_eq!(drawn(&pixels), expected);
+        for &(x, y) in &expected {
+            let i = ((y * SIZE + x) * 4) as usize;
+            assert_eq!(&pixels[i..i + 4], &[128, 128, 128, 255], "pixel ({}, {})", x, y);
+        }
+    }
+
+    #[test]
+    fn shared_diagonal_covers_each_pixel_once() {
+        let half = [255, 255, 255, 128];
+        let vertices = [vertex(0.3, 1.2, 0.0, 0.0, half), vertex(7.1, 0.4, 0.0, 0.0, half), vertex(6.6, 7.8, 0.0, 0.0, half), vertex(1.4, 6.2, 0.0, 0.0, half)];
+        let pixels = draw(&vertices, &[0, 1, 2, 0, 2, 3], EVERYWHERE, None);
+
+        assert!(drawn(&pixels).len() > 30);
+        assert!(pixels.chunks(4).all(|p| p == [0, 0, 0, 0] || p == [128, 128, 128, 255]));
+    }
+
+    #[test]
+    fn degenerate_triangles_draw_nothing() {
+        let white = [255; 4];
+        let vertices = [vertex(0.0, 0.0, 0.0, 0.0, white), vertex(4.0, 4.0, 0.0, 0.0, white), vertex(8.0, 8.0, 0.0, 0.0, white)];
+        assert!(drawn(&draw(&vertices, &[0, 1, 2, 0, 1], EVERYWHERE, None)).is_empty());
+    }
+
+    #[test]
+    fn textures_are_sampled_per_texel_and_tinted() {
+        let texels = [255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255, 255, 255, 255, 255];
+        let texture = Texture { width: 2, height: 2, format: TextureFormat::Rgba, pixels: &texels, normalized: true };
+        let tint = [255, 255, 0, 255];
+        let vertices = [vertex(0.0, 0.0, 0.0, 0.0, tint), vertex(4.0, 0.0, 1.0, 0.0, tint), vertex(4.0, 4.0, 1.0, 1.0, tint), vertex(0.0, 4.0, 0.0, 1.0, tint)];
+        let pixels = draw(&vertices, &[0, 1, 2, 0, 2, 3], EVERYWHERE, Some(&texture));
+
+        let at = |x: i32, y: i32| pixels[((y * SIZE + x) * 4) as usize..((y * SIZE + x) * 4 + 3) as usize].to_vec();
+        for &(x, y) in &[(0, 0), (1, 1)] {
+            assert_eq!(at(x, y), vec![255, 0, 0]);
+        }
+        for &(x, y) in &[(2, 0), (3, 1)] {
+            assert_eq!(at(x, y), vec![0, 255, 0]);
+        }
+        // blue is tinted away
+        for &(x, y) in &[(0, 2), (1, 3)] {
+            assert_eq!(at(x, y), vec![0, 0, 0]);
+        }
+        for &(x, y) in &[(2, 2), (3, 3)] {
+            assert_eq!(at(x, y), vec![255, 255, 0]);
+        }
+        assert_eq!(at(4, 0), vec![0, 0, 0]);
+    }
+
+    #[test]
+    fn alpha_textures_are_coverage() {
+        let texels = [0, 255];
+        let texture = Texture { width: 2, height: 1, format: TextureFormat::Alpha, pixels: &texels, normalized: false };
+        let red = [255, 0, 0, 255];
+        let vertices = [vertex(0.0, 0.0, 0.0, 0.0, red), vertex(4.0, 0.0, 2.0, 0.0, red), vertex(4.0, 1.0, 2.0, 1.0, red), vertex(0.0, 1.0, 0.0, 1.0, red)];
+        let pixels = draw(&vertices, &[0, 1, 2, 0, 2, 3], EVERYWHERE, Some(&texture));
+
+        assert_eq!(drawn(&pixels), vec![(2, 0), (3, 0)]);
+        assert_eq!(&pixels[8..12], &[255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn triangles_are_clipped() {
+        let white = [255; 4];
+        let vertices = [vertex(-5.0, -5.0, 0.0, 0.0, white), vertex(20.0, -5.0, 0.0, 0.0, white), vertex(20.0, 20.0, 0.0, 0.0, white), vertex(-5.0, 20.0, 0.0, 0.0, white)];
+        let indices = [0, 1, 2, 0, 2, 3];
+
+        let expected: Vec<(i32, i32)> = (3..6).flat_map(|y| (2..5).map(move |x| (x, y))).collect();
+        assert_eq!(drawn(&draw(&vertices, &indices, (2, 3, 5, 6), None)), expected);
+
+        // a clip reaching past the target is cut to it
+        assert_eq!(drawn(&draw(&vertices, &indices, (-10, -10, 100, 100), None)).len(), (SIZE * SIZE) as usize);
+        assert!(drawn(&draw(&vertices, &indices, (4, 4, 4, 8), None)).is_empty());
+    }
+}
@@ -0,0 +1,242 @@
+use crate::surface::{convert_color, Surface};
+use nuklear::*;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Primitive {
+    Clear,
+    Scissor { x: i32, y: i32, w: i32, h: i32 },
+    Line { x0: i32, y0: i32, x1: i32, y1: i32 },
+    Rect { x: i32, y: i32, w: i32, h: i32, rounding: i32, filled: bool },
+    RectMultiColor { x: i32, y: i32, w: i32, h: i32, left: u32, top: u32, right: u32, bottom: u32 },
+    Triangle { points: [(i32, i32); 3], filled: bool },
+    Polygon { points: Vec<(i32, i32)>, filled: bool },
+    Polyline { points: Vec<(i32, i32)> },
+    Arc { cx: i32, cy: i32, r: u32, a1: f32, a2: f32, filled: bool },
+    Circle { x: i32, y: i32, w: i32, h: i32, filled: bool },
+    Curve { points: [(i32, i32); 4] },
+    Image { x: i32, y: i32, w: i32, h: i32, handle: usize },
+    Text { x: i32, y: i32, w: i32, h: i32, text: String, background: u32 },
+    Custom { x: i32, y: i32, w: i32, h: i32, data: usize },
+}
+
+/// One drawing call as the GDI backend would issue it.
+///
+/// `color` is the `COLORREF` produced by `convert_color` (the foreground for text, the `left` corner for `RectMultiColor`, whose four
+/// corners are kept in the primitive), `line_thickness` is the pen width (0 for fills) and `clip` is the rectangle last passed to
+/// `IntersectClipRect` as `(left, top, right, bottom)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub primitive: Primitive,
+    pub line_thickness: i32,
+    pub color: u32,
+    pub alpha: u8,
+    pub clip: Option<(i32, i32, i32, i32)>,
+}
+
+/// A `Surface` that draws nothing and keeps every call as a `Record`.
+#[derive(Default)]
+pub struct RecordingSurface {
+    records: Vec<Record>,
+    clip: Option<(i32, i32, i32, i32)>,
+}
+
+impl RecordingSurface {
+    pub fn new() -> RecordingSurface {
+        RecordingSurface::default()
+    }
+
+    pub fn records(&self) -> &[Record] {
+        &self.records
+    }
+
+    pub fn take_records(&mut self) -> Vec<Record> {
+        ::std::mem::replace(&mut self.records, Vec::new())
+    }
+
+    fn push(&mut self, primitive: Primitive, line_thickness: i32, col: Color) {
+        self.records.push(Record {
+            primitive: primitive,
+            line_thickness: line_thickness,
+            color: convert_color(col),
+            alpha: col.a,
+            clip: self.clip,
+        });
+    }
+}
+
+fn to_points(points: &[Vec2i]) -> Vec<(i32, i32)> {
+    points.iter().map(|p| (p.x as i32, p.y as i32)).collect()
+}
+
+impl Surface for RecordingSurface {
+    // Like the GDI surface, clearing drops the scissor of the previous frame first.
+    fn clear(&mut self, col: Color) {
+        self.clip = None;
+        self.push(Primitive::Clear, 0, col);
+    }
+
+    fn scissor(&mut self, x: i32, y: i32, w: i32, h: i32) {
+        self.clip = Some((x, y, x + w, y + h));
+        self.push(Primitive::Scissor { x: x, y: y, w: w, h: h }, 0, Color { r: 0, g: 0, b: 0, a: 0 });
+    }
+
+    fn stroke_line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, line_thickness: i32, col: Color) {
+        self.push(Primitive::Line { x0: x0, y0: y0, x1: x1, y1: y1 }, line_thickness, col);
+    }
+
+    fn stroke_rect(&mut self, x: i32, y: i32, w: i32, h: i32, r: i32, line_thickness: i32, col: Color) {
+        self.push(Primitive::Rect { x: x, y: y, w: w, h: h, rounding: r, filled: false }, line_thickness, col);
+    }
+
+    fn fill_rect(&mut self, x: i32, y: i32, w: i32, h: i32, r: i32, col: Color) {
+        self.push(Primitive::Rect { x: x, y: y, w: w, h: h, rounding: r, filled: true }, 0, col);
+    }
+
+    fn fill_rect_multi_color(&mut self, x: i32, y: i32, w: i32, h: i32, left: Color, top: Color, right: Color, bottom: Color) {
+        let primitive = Primitive::RectMultiColor {
+            x: x,
+            y: y,
+            w: w,
+            h: h,
+            left: convert_color(left),
+            top: convert_color(top),
+            right: convert_color(right),
+            bottom: convert_color(bottom),
+        };
+        self.push(primitive, 0, left);
+    }
+
+    fn stroke_triangle(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, x2: i32, y2: i32, line_thickness: i32, col: Color) {
+        self.push(Primitive::Triangle { points: [(x0, y0), (x1, y1), (x2, y2)], filled: false }, line_thickness, col);
+    }
+
+    fn fill_triangle(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, x2: i32, y2: i32, col: Color) {
+        self.push(Primitive::Triangle { points: [(x0, y0), (x1, y1), (x2, y2)], filled: true }, 0, col);
+    }
+
+    fn stroke_polygon(&mut self, points: &[Vec2i], line_thickness: i32, col: Color) {
+        self.push(Primitive::Polygon { points: to_points(points), filled: false }, line_thickness, col);
+    }
+
+    fn fill_polygon(&mut self, points: &[Vec2i], col: Color) {
+        self.push(Primitive::Polygon { points: to_points(points), filled: true }, 0, col);
+    }
+
+    fn stroke_polyline(&mut self, points: &[Vec2i], line_thickness: i32, col: Color) {
+        self.push(Primitive::Polyline { points: to_points(points) }, line_thickness, col);
+    }
+
+    fn stroke_arc(&mut self, cx: i32, cy: i32, r: u32, a1: f32, a2: f32, line_thickness: i32, col: Color) {
+        self.push(Primitive::Arc { cx: cx, cy: cy, r: r, a1: a1, a2: a2, filled: false }, line_thickness, col);
+    }
+
+    fn fill_arc(&mut self, cx: i32, cy: i32, r: u32, a1: f32, a2: f32, col: Color) {
+        self.push(Primitive::Arc { cx: cx, cy: cy, r: r, a1: a1, a2: a2, filled: true }, 0, col);
+    }
+
+    fn stroke_circle(&mut self, x: i32, y: i32, w: i32, h: i32, line_thickness: i32, col: Color) {
+        self.push(Primitive::Circle { x: x, y: y, w: w, h: h, filled: false }, line_thickness, col);
+    }
+
+    fn fill_circle(&mut self, x: i32, y: i32, w: i32, h: i32, col: Color) {
+        self.push(Primitive::Circle { x: x, y: y, w: w, h: h, filled: true }, 0, col);
+    }
+
+    fn stroke_curve(&mut self, p1: Vec2i, p2: Vec2i, p3: Vec2i, p4: Vec2i, line_thickness: i32, col: Color) {
+        let points = [(p1.x as i32, p1.y as i32), (p2.x as i32, p2.y as i32), (p3.x as i32, p3.y as i32), (p4.x as i32, p4.y as i32)];
+        self.push(Primitive::Curve { points: points }, line_thickness, col);
+    }
+
+    fn draw_image(&mut self, x: i32, y: i32, w: i32, h: i32, mut img: Image, col: Color) {
+        self.push(Primitive::Image { x: x, y: y, w: w, h: h, handle: img.ptr() as usize }, 0, col);
+    }
+
+    fn draw_text(&mut self, x: i32, y: i32, w: i32, h: i32, text: &[u8], _: &UserFont, cbg: Color, cfg: Color) {
+        let text = String::from_utf8_lossy(text).into_owned();
+        self.push(Primitive::Text { x: x, y: y, w: w, h: h, text: text, background: convert_color(cbg) }, 0, cfg);
+    }
+
+    fn draw_custom(&mut self, x: i32, y: i32, w: i32, h: i32, mut data: Handle) {
+        let data = data.ptr().map(|p| p as usize).unwrap_or(0);
+        self.push(Primitive::Custom { x: x, y: y, w: w, h: h, data: data }, 0, Color { r: 0, g: 0, b: 0, a: 0 });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::{Frame, FrameCommand};
+
+    const RED: Color = Color { r: 0xff, g: 0, b: 0, a: 0xff };
+    const BLUE: Color = Color { r: 0, g: 0, b: 0xff, a: 0x80 };
+
+    fn record(primitive: Primitive, line_thickness: i32, color: u32, alpha: u8, clip: Option<(i32, i32, i32, i32)>) -> Record {
+        Record {
+            primitive: primitive,
+            line_thickness: line_thickness,
+            color: color,
+            alpha: alpha,
+            clip: clip,
+        }
+    }
+
+    #[test]
+    fn strokes_keep_their_width_and_fills_have_none() {
+        let mut surface = RecordingSurface::new();
+        surface.stroke_line(1, 2, 3, 4, 3, RED);
+        surface.fill_rect(5, 6, 7, 8, 2, BLUE);
+        surface.stroke_polyline(&[Vec2i { x: 0, y: 0 }, Vec2i { x: 9, y: 9 }], 5, BLUE);
+
+        assert_eq!(
+            surface.records(),
+            &[
+                record(Primitive::Line { x0: 1, y0: 2, x1: 3, y1: 4 }, 3, 0x0000_00ff, 0xff, None),
+                record(Primitive::Rect { x: 5, y: 6, w: 7, h: 8, rounding: 2, filled: true }, 0, 0x00ff_0000, 0x80, None),
+                record(Primitive::Polyline { points: vec![(0, 0), (9, 9)] }, 5, 0x00ff_0000, 0x80, None),
+            ][..]
+        );
+    }
+
+    #[test]
+    fn replayed_commands_record_their_width_and_color() {
+        let frame = Frame {
+            commands: vec![
+                FrameCommand::Rect { x: 1, y: 2, w: 3, h: 4, rounding: 0, line_thickness: 2, color: [0x11, 0x22, 0x33, 0xff] },
+                FrameCommand::Circle { x: 5, y: 6, w: 7, h: 8, line_thickness: 4, color: [0x44, 0x55, 0x66, 0x40] },
+                FrameCommand::TriangleFilled { a: (0, 0), b: (4, 0), c: (0, 4), color: [1, 2, 3, 4] },
+            ],
+        };
+        let mut surface = RecordingSurface::new();
+        frame.replay(&mut surface, |_| None, |_| None);
+
+        assert_eq!(
+            surface.take_records(),
+            vec![
+                record(Primitive::Rect { x: 1, y: 2, w: 3, h: 4, rounding: 0, filled: false }, 2, 0x0033_2211, 0xff, None),
+                record(Primitive::Circle { x: 5, y: 6, w: 7, h: 8, filled: false }, 4, 0x0066_5544, 0x40, None),
+                record(Primitive::Triangle { points: [(0, 0), (4, 0), (0, 4)], filled: true }, 0, 0x0003_0201, 4, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn multi_color_rects_record_the_left_corner_as_their_color() {
+        let mut surface = RecordingSurface::new();
+        surface.fill_rect_multi_color(0, 0, 4, 4, BLUE, RED, RED, RED);
+
+        let primitive = Primitive::RectMultiColor { x: 0, y: 0, w: 4, h: 4, left: 0x00ff_0000, top: 0xff, right: 0xff, bottom: 0xff };
+        assert_eq!(surface.records(), &[record(primitive, 0, 0x00ff_0000, 0x80, None)][..]);
+    }
+
+    #[test]
+    fn scissors_clip_later_records_until_clear() {
+        let mut surface = RecordingSurface::new();
+        surface.scissor(1, 2, 10, 20);
+        surface.fill_rect(0, 0, 4, 4, 0, RED);
+        surface.clear(RED);
+        surface.fill_rect(0, 0, 4, 4, 0, RED);
+
+        let clips: Vec<_> = surface.records().iter().map(|r| r.clip).collect();
+        assert_eq!(clips, vec![Some((1, 2, 11, 22)), Some((1, 2, 11, 22)), None, None]);
+    }
+}
@@ -0,0 +1,739 @@
+use crate::surface::{self, FillRule, LineCap, LineJoin, Surface, MITER_LIMIT};
+use nuklear::nuklear_sys as nksys;
+use nuklear::*;
+use std::f32::consts::PI;
+
+const CURVE_SEGMENTS: usize = 16;
+
+/// A rasterized glyph: `coverage` holds `width * height` alpha values, row by row.
+pub struct Glyph {
+    pub width: u32,
+    pub height: u32,
+    pub x_offset: i32,
+    pub y_offset: i32,
+    pub advance: i32,
+    pub coverage: Vec<u8>,
+}
+
+/// Supplies glyph bitmaps for text drawn by `SoftwareSurface`.
+pub trait GlyphSource {
+    fn glyph(&self, font: &UserFont, c: char) -> Option<Glyph>;
+}
+
+/// Draws every character but whitespace as a box half the font height wide, so text stays visible without real fonts.
+/// Inner rows are filled from the low bits of the code point, so replacing a character changes pixels too.
+/// `SoftwareSurface` uses it until another source is set.
+pub struct BoxGlyphs;
+
+impl GlyphSource for BoxGlyphs {
+    fn glyph(&self, font: &UserFont, c: char) -> Option<Glyph> {
+        let height = unsafe { (*(font as *const _ as *const nksys::nk_user_font)).height } as i32;
+        let advance = (height / 2).max(1);
+        if c.is_whitespace() {
+            return Some(Glyph {
+                width: 0,
+                height: 0,
+                x_offset: 0,
+                y_offset: 0,
+                advance: advance,
+                coverage: Vec::new(),
+            });
+        }
+
+        // leaves a column between characters and the top quarter of the line free, as ascenders would
+        let (width, top) = ((advance - 1).max(1), height / 4);
+        let rows = (height - top).max(0);
+        let mut coverage = Vec::with_capacity((width * rows) as usize);
+        for gy in 0..rows {
+            for gx in 0..width {
+                let outline = gx == 0 || gx == width - 1 || gy == 0 || gy == rows - 1;
+                coverage.push(if outline || (c as u32 >> ((gy - 1) % 16)) & 1 != 0 { 255 } else { 0 });
+            }
+        }
+        Some(Glyph {
+            width: width as u32,
+            height: rows as u32,
+            x_offset: 0,
+            y_offset: top,
+            advance: advance,
+            coverage: coverage,
+        })
+    }
+}
+
+struct SoftwareImage {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+/// CPU rasterizer drawing into an RGBA buffer, following the geometry conventions of the GDI backend.
+pub struct SoftwareSurface {
+    width: i32,
+    height: i32,
+    pixels: Vec<u8>,
+    clip: (i32, i32, i32, i32),
+    images: Vec<SoftwareImage>,
+    glyph_source: Option<Box<dyn GlyphSource>>,
+    fill_rule: FillRule,
+    join: LineJoin,
+    cap: LineCap,
+    // per-pixel coverage of the translucent shape being drawn, and the pixels it touched
+    mask: Vec<u8>,
+    touched: Option<Vec<usize>>,
+}
+
+impl SoftwareSurface {
+    pub fn new(width: u16, height: u16) -> SoftwareSurface {
+        SoftwareSurface {
+            width: width as i32,
+            height: height as i32,
+            pixels: vec![0; width as usize * height as usize * 4],
+            clip: (0, 0, width as i32, height as i32),
+            images: Vec::new(),
+            glyph_source: Some(Box::new(BoxGlyphs)),
+            fill_rule: FillRule::default(),
+            join: LineJoin::Miter,
+            cap: LineCap::Square,
+            mask: vec![0; width as usize * height as usize],
+            touched: None,
+        }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    /// Row-major RGBA pixels, top row first.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn pixel(&self, x: i32, y: i32) -> [u8; 4] {
+        let i = ((y * self.width + x) * 4) as usize;
+        [self.pixels[i], self.pixels[i + 1], self.pixels[i + 2], self.pixels[i + 3]]
+    }
+
+    /// Rule for `fill_polygon`; triangles and other convex shapes are unaffected.
+    pub fn set_fill_rule(&mut self, rule: FillRule) {
+        self.fill_rule = rule;
+    }
+
+    /// Joins and caps of strokes thicker than one pixel; miter joins and square caps by default, as in `GdiSurface`.
+    pub fn set_stroke_style(&mut self, join: LineJoin, cap: LineCap) {
+        self.join = join;
+        self.cap = cap;
+    }
+
+    pub fn set_glyph_source(&mut self, source: Box<dyn GlyphSource>) {
+        self.glyph_source = Some(source);
+    }
+
+    /// Registers straight-alpha RGBA pixels (top row first) and returns the handle to put into a nuklear `Image`.
+    pub fn add_image(&mut self, width: u32, height: u32, rgba: &[u8]) -> Handle {
+        assert_eq!(rgba.len(), (width * height * 4) as usize);
+
+        self.images.push(SoftwareImage {
+            width: width,
+            height: height,
+            pixels: rgba.to_vec(),
+        });
+        Handle::from_id(self.images.len() as i32 - 1)
+    }
+
+    #[cfg(feature = "piston_image")]
+    pub fn add_piston_image(&mut self, img: &image::DynamicImage) -> Handle {
+        let img = img.to_rgba();
+        let (w, h) = img.dimensions();
+        self.add_image(w, h, &img.into_raw())
+    }
+
+    #[cfg(feature = "piston_image")]
+    pub fn to_image(&self) -> image::RgbaImage {
+        image::RgbaImage::from_raw(self.width as u32, self.height as u32, self.pixels.clone()).unwrap()
+    }
+
+    pub fn render(&mut self, ctx: &mut Context, clear: Color) {
+        self.clear(clear);
+        surface::draw_commands(self, ctx);
+        ctx.clear();
+    }
+
+    // `coverage` is scaled by `col.a`
+    fn blend(&mut self, x: i32, y: i32, col: Color, coverage: u8) {
+        let (cx0, cy0, cx1, cy1) = self.clip;
+        if x < cx0 || y < cy0 || x >= cx1 || y >= cy1 || coverage == 0 {
+            return;
+        }
+
+        let i = (y * self.width + x) as usize;
+        if let Some(ref mut touched) = self.touched {
+            if self.mask[i] == 0 {
+                touched.push(i);
+            }
+            self.mask[i] = self.mask[i].max(coverage);
+            return;
+        }
+        self.mix(i, col, (coverage as u32 * col.a as u32 / 255) as u8);
+    }
+
+    fn mix(&mut self, i: usize, col: Color, alpha: u8) {
+        let a = alpha as u32;
+        let src = [col.r, col.g, col.b];
+        for (c, s) in src.iter().enumerate() {
+            let d = self.pixels[i * 4 + c] as u32;
+            self.pixels[i * 4 + c] = ((*s as u32 * a + d * (255 - a)) / 255) as u8;
+        }
+        self.pixels[i * 4 + 3] = 255;
+    }
+
+    fn put(&mut self, x: i32, y: i32, col: Color) {
+        self.blend(x, y, col, 255);
+    }
+
+    // Draws one primitive. A translucent one is rasterized into `mask` first, so pixels it covers twice (outlines, joints) are blended once.
+    fn shape<F: FnOnce(&mut SoftwareSurface, Color)>(&mut self, col: Color, draw: F) {
+        match col.a {
+            0 => {}
+            255 => draw(self, col),
+            _ => {
+                self.touched = Some(Vec::new());
+                draw(self, Color { a: 255, ..col });
+                for i in self.touched.take().unwrap() {
+                    let alpha = (self.mask[i] as u32 * col.a as u32 / 255) as u8;
+                    self.mask[i] = 0;
+                    self.mix(i, col, alpha);
+                }
+            }
+        }
+    }
+
+    fn span(&mut self, x0: i32, x1: i32, y: i32, col: Color) {
+        for x in x0..x1 {
+            self.put(x, y, col);
+        }
+    }
+
+    // Bresenham, omitting the final pixel like GDI's LineTo.
+    fn thin_line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, col: Color) {
+        let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+        let (sx, sy) = (if x0 < x1 { 1 } else { -1 }, if y0 < y1 { 1 } else { -1 });
+        let (mut x, mut y, mut err) = (x0, y0, dx + dy);
+
+        while x != x1 || y != y1 {
+            self.put(x, y, col);
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+
+    // Covers every pixel whose center lies within `half` of (x, y).
+    fn dot(&mut self, x: f32, y: f32, half: f32, col: Color) {
+        for py in (y - half).floor() as i32..=(y + half).ceil() as i32 {
+            for px in (x - half).floor() as i32..=(x + half).ceil() as i32 {
+                let (dx, dy) = (px as f32 - x, py as f32 - y);
+                if dx * dx + dy * dy <= half * half {
+                    self.put(px, py, col);
+                }
+            }
+        }
+    }
+
+    fn stroke_path(&mut self, points: &[(f32, f32)], closed: bool, line_thickness: i32, col: Color) {
+        if points.is_empty() {
+            return;
+        }
+
+        let mut segments: Vec<((f32, f32), (f32, f32))> = points.windows(2).map(|w| (w[0], w[1])).collect();
+        if closed && points.len() > 1 {
+            segments.push((points[points.len() - 1], points[0]));
+        }
+
+        if line_thickness <= 1 {
+            for &((x0, y0), (x1, y1)) in &segments {
+                self.thin_line(x0.round() as i32, y0.round() as i32, x1.round() as i32, y1.round() as i32, col);
+            }
+        } else {
+            segments.retain(|&(a, b)| a != b);
+            self.thick_path(&segments, closed, line_thickness as f32 / 2.0, col);
+        }
+    }
+
+    // A geometric pen: every segment is a rectangle `2 * half` wide, with the joins and caps of the stroke style added on top.
+    fn thick_path(&mut self, segments: &[((f32, f32), (f32, f32))], closed: bool, half: f32, col: Color) {
+        if segments.is_empty() {
+            return;
+        }
+
+        let last = segments.len() - 1;
+        for (i, &((x0, y0), (x1, y1))) in segments.iter().enumerate() {
+            let len = ((x1 - x0) * (x1 - x0) + (y1 - y0) * (y1 - y0)).sqrt();
+            let (dx, dy) = ((x1 - x0) / len, (y1 - y0) / len);
+            let (nx, ny) = (-dy * half, dx * half);
+            // square caps lengthen the open ends of the path
+            let head = if !closed && i == 0 && self.cap == LineCap::Square { half } else { 0.0 };
+            let tail = if !closed && i == last && self.cap == LineCap::Square { half } else { 0.0 };
+            let (ax, ay) = (x0 - dx * head, y0 - dy * head);
+            let (bx, by) = (x1 + dx * tail, y1 + dy * tail);
+            self.fill_path(&[(ax + nx, ay + ny), (bx + nx, by + ny), (bx - nx, by - ny), (ax - nx, ay - ny)], FillRule::Winding, col);
+        }
+
+        if closed {
+            for i in 0..segments.len() {
+                self.stroke_join(segments[i], segments[(i + 1) % segments.len()], half, col);
+            }
+        } else {
+            for pair in segments.windows(2) {
+                self.stroke_join(pair[0], pair[1], half, col);
+            }
+            if self.cap == LineCap::Round {
+                let ((x0, y0), _) = segments[0];
+                let (_, (x1, y1)) = segments[last];
+                self.dot(x0, y0, half, col);
+                self.dot(x1, y1, half, col);
+            }
+        }
+    }
+
+    // Fills the wedge between the outer corners of two consecutive segments meeting at `b.0`.
+    fn stroke_join(&mut self, a: ((f32, f32), (f32, f32)), b: ((f32, f32), (f32, f32)), half: f32, col: Color) {
+        let (x, y) = b.0;
+        if self.join == LineJoin::Round {
+            self.dot(x, y, half, col);
+            return;
+        }
+
+        let unit = |((x0, y0), (x1, y1)): ((f32, f32), (f32, f32))| {
+            let len = ((x1 - x0) * (x1 - x0) + (y1 - y0) * (y1 - y0)).sqrt();
+            ((x1 - x0) / len, (y1 - y0) / len)
+        };
+        let ((ax, ay), (bx, by)) = (unit(a), unit(b));
+        let turn = ax * by - ay * bx;
+        if turn == 0.0 && ax * bx + ay * by > 0.0 {
+            return;
+        }
+
+        // the outer side lies against the turn
+        let side = if turn > 0.0 { -half } else { half };
+        let (n0x, n0y) = (-ay * side, ax * side);
+        let (n1x, n1y) = (-by * side, bx * side);
+        let (mx, my) = (n0x + n1x, n0y + n1y);
+        let m = (mx * mx + my * my).sqrt();
+        // length of the miter from the inner to the outer corner, in line widths
+        let ratio = if m > 0.0 { half * m / (mx * n0x + my * n0y) } else { f32::MAX };
+
+        if self.join == LineJoin::Miter && ratio <= MITER_LIMIT {
+            let scale = ratio * half / m;
+            self.fill_path(&[(x, y), (x + n0x, y + n0y), (x + mx * scale, y + my * scale), (x + n1x, y + n1y)], FillRule::Winding, col);
+        } else {
+            self.fill_path(&[(x, y), (x + n0x, y + n0y), (x + n1x, y + n1y)], FillRule::Winding, col);
+        }
+    }
+
+    // Scanline fill under `rule`, sampled at pixel centers; vertices address pixel centers as in GDI.
+    fn fill_path(&mut self, points: &[(f32, f32)], rule: FillRule, col: Color) {
+        if points.len() < 3 {
+            return;
+        }
+
+        let ymin = points.iter().fold(f32::MAX, |m, p| m.min(p.1)).floor() as i32;
+        let ymax = points.iter().fold(f32::MIN, |m, p| m.max(p.1)).ceil() as i32;
+        let mut xs = Vec::new();
+
+        for py in ymin..=ymax {
+            let yc = py as f32;
+            xs.clear();
+            for i in 0..points.len() {
+                let (x0, y0) = points[i];
+                let (x1, y1) = points[(i + 1) % points.len()];
+                if (y0 <= yc && yc < y1) || (y1 <= yc && yc < y0) {
+                    xs.push((x0 + (yc - y0) * (x1 - x0) / (y1 - y0), if y1 > y0 { 1 } else { -1 }));
+                }
+            }
+            xs.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+            // the winding number changes at every crossing and stays put up to the next one
+            let mut winding = 0;
+            for pair in xs.windows(2) {
+                winding += pair[0].1;
+                if rule.contains(winding) {
+                    self.span(pair[0].0.ceil() as i32, pair[1].0.floor() as i32 + 1, py, col);
+                }
+            }
+        }
+    }
+
+    fn fill_outlined_path(&mut self, points: &[(f32, f32)], rule: FillRule, col: Color) {
+        self.fill_path(points, rule, col);
+        self.stroke_path(points, true, 1, col);
+    }
+
+    // Ellipse inscribed into [x, x + w) x [y, y + h), optionally limited to an angular sweep.
+    // `line_thickness == 0` fills the shape, anything else draws a ring with the pen centered on the outline.
+    fn ellipse(&mut self, x: i32, y: i32, w: i32, h: i32, line_thickness: i32, sweep: Option<(f32, f32)>, col: Color) {
+        let (cx, cy) = (x as f32 + w as f32 / 2.0, y as f32 + h as f32 / 2.0);
+        let (rx, ry) = (w as f32 / 2.0, h as f32 / 2.0);
+        let t = line_thickness as f32;
+        let (outer, inner) = if line_thickness == 0 { (0.0, None) } else { (t / 2.0 - 0.5, Some(t / 2.0 + 0.5)) };
+        let pad = outer.ceil() as i32 + 1;
+
+        for py in (y - pad)..(y + h + pad) {
+            for px in (x - pad)..(x + w + pad) {
+                let (dx, dy) = (px as f32 + 0.5 - cx, py as f32 + 0.5 - cy);
+                if !inside_ellipse(dx, dy, rx + outer, ry + outer) {
+                    continue;
+                }
+                if let Some(inner) = inner {
+                    if inside_ellipse(dx, dy, rx - inner, ry - inner) {
+                        continue;
+                    }
+                }
+                if let Some((a1, a2)) = sweep {
+                    if !in_sweep(dy.atan2(dx), a1, a2) {
+                        continue;
+                    }
+                }
+                self.put(px, py, col);
+            }
+        }
+    }
+
+    fn round_rect(&mut self, x: i32, y: i32, w: i32, h: i32, r: i32, line_thickness: i32, col: Color) {
+        let (l, t, rt, b) = (x as f32, y as f32, (x + w) as f32, (y + h) as f32);
+        let rad = surface::corner_radius(w, h, r) as f32;
+        let lt = line_thickness as f32;
+        let (outer, inner) = if line_thickness == 0 { (0.0, None) } else { (lt / 2.0 - 0.5, Some(lt / 2.0 + 0.5)) };
+        let pad = outer.ceil() as i32 + 1;
+
+        for py in (y - pad)..(y + h + pad) {
+            for px in (x - pad)..(x + w + pad) {
+                let (qx, qy) = (px as f32 + 0.5, py as f32 + 0.5);
+                if !inside_round_rect(qx, qy, l - outer, t - outer, rt + outer, b + outer, rad + outer) {
+                    continue;
+                }
+                if let Some(inner) = inner {
+                    if inside_round_rect(qx, qy, l + inner, t + inner, rt - inner, b - inner, (rad - inner).max(0.0)) {
+                        continue;
+                    }
+                }
+                self.put(px, py, col);
+            }
+        }
+    }
+}
+
+fn inside_ellipse(dx: f32, dy: f32, rx: f32, ry: f32) -> bool {
+    if rx <= 0.0 || ry <= 0.0 {
+        return false;
+    }
+    (dx * dx) / (rx * rx) + (dy * dy) / (ry * ry) <= 1.0
+}
+
+fn inside_round_rect(x: f32, y: f32, l: f32, t: f32, r: f32, b: f32, rad: f32) -> bool {
+    if x < l || x > r || y < t || y > b {
+        return false;
+    }
+    let cx = if x < l + rad { l + rad } else if x > r - rad { r - rad } else { x };
+    let cy = if y < t + rad { t + rad } else if y > b - rad { b - rad } else { y };
+    let (dx, dy) = (x - cx, y - cy);
+    dx * dx + dy * dy <= rad * rad
+}
+
+// Angles grow clockwise on screen (y points down); a negative sweep runs counter-clockwise.
+fn in_sweep(angle: f32, a1: f32, a2: f32) -> bool {
+    let sweep = a2 - a1;
+    if sweep.abs() < ::std::f32::EPSILON {
+        return false;
+    }
+    if sweep.abs() >= 2.0 * PI {
+        return true;
+    }
+    if sweep >= 0.0 {
+        (angle - a1).rem_euclid(2.0 * PI) <= sweep
+    } else {
+        (a1 - angle).rem_euclid(2.0 * PI) <= -sweep
+    }
+}
+
+fn to_path(points: &[Vec2i]) -> Vec<(f32, f32)> {
+    points.iter().map(|p| (p.x as f32, p.y as f32)).collect()
+}
+
+impl Surface for SoftwareSurface {
+    fn clear(&mut self, col: Color) {
+        self.clip = (0, 0, self.width, self.height);
+        for p in self.pixels.chunks_mut(4) {
+            p.copy_from_slice(&[col.r, col.g, col.b, 255]);
+        }
+    }
+
+    fn scissor(&mut self, x: i32, y: i32, w: i32, h: i32) {
+        self.clip = (x.max(0), y.max(0), (x + w).min(self.width), (y + h).min(self.height));
+    }
+
+    fn stroke_line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, line_thickness: i32, col: Color) {
+        self.shape(col, |s, col| s.stroke_path(&[(x0 as f32, y0 as f32), (x1 as f32, y1 as f32)], false, line_thickness, col));
+    }
+
+    fn stroke_rect(&mut self, x: i32, y: i32, w: i32, h: i32, r: i32, line_thickness: i32, col: Color) {
+        self.shape(col, |s, col| s.round_rect(x, y, w, h, r, line_thickness.max(1), col));
+    }
+
+    fn fill_rect(&mut self, x: i32, y: i32, w: i32, h: i32, r: i32, col: Color) {
+        self.shape(col, |s, col| {
+            if r == 0 {
+                for py in y..(y + h) {
+                    s.span(x, x + w, py, col);
+                }
+            } else {
+                s.round_rect(x, y, w, h, r, 0, col);
+            }
+        });
+    }
+
+    fn fill_rect_multi_color(&mut self, x: i32, y: i32, w: i32, h: i32, left: Color, top: Color, right: Color, bottom: Color) {
+        let lerp = |a: Color, b: Color, t: f32| Color {
+            r: (a.r as f32 + (b.r as f32 - a.r as f32) * t).round() as u8,
+            g: (a.g as f32 + (b.g as f32 - a.g as f32) * t).round() as u8,
+            b: (a.b as f32 + (b.b as f32 - a.b as f32) * t).round() as u8,
+            a: (a.a as f32 + (b.a as f32 - a.a as f32) * t).round() as u8,
+        };
+
+        for py in 0..h {
+            let v = if h > 1 { py as f32 / (h - 1) as f32 } else { 0.0 };
+            for px in 0..w {
+                let u = if w > 1 { px as f32 / (w - 1) as f32 } else { 0.0 };
+                let col = lerp(lerp(left, top, u), lerp(bottom, right, u), v);
+                self.put(x + px, y + py, col);
+            }
+        }
+    }
+
+    fn stroke_triangle(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, x2: i32, y2: i32, line_thickness: i32, col: Color) {
+        self.shape(col, |s, col| s.stroke_path(&[(x0 as f32, y0 as f32), (x1 as f32, y1 as f32), (x2 as f32, y2 as f32)], true, line_thickness, col));
+    }
+
+    fn fill_triangle(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, x2: i32, y2: i32, col: Color) {
+        self.shape(col, |s, col| s.fill_outlined_path(&[(x0 as f32, y0 as f32), (x1 as f32, y1 as f32), (x2 as f32, y2 as f32)], FillRule::Alternate, col));
+    }
+
+    fn stroke_polygon(&mut self, points: &[Vec2i], line_thickness: i32, col: Color) {
+        self.shape(col, |s, col| s.stroke_path(&to_path(points), true, line_thickness, col));
+    }
+
+    fn fill_polygon(&mut self, points: &[Vec2i], col: Color) {
+        let rule = self.fill_rule;
+        self.shape(col, |s, col| s.fill_outlined_path(&to_path(points), rule, col));
+    }
+
+    fn stroke_polyline(&mut self, points: &[Vec2i], line_thickness: i32, col: Color) {
+        self.shape(col, |s, col| s.stroke_path(&to_path(points), false, line_thickness, col));
+    }
+
+    fn stroke_arc(&mut self, cx: i32, cy: i32, r: u32, a1: f32, a2: f32, line_thickness: i32, col: Color) {
+        let r = r as i32;
+        self.shape(col, |s, col| s.ellipse(cx - r, cy - r, r * 2, r * 2, line_thickness.max(1), Some((a1, a2)), col));
+    }
+
+    fn fill_arc(&mut self, cx: i32, cy: i32, r: u32, a1: f32, a2: f32, col: Color) {
+        let r = r as i32;
+        self.shape(col, |s, col| s.ellipse(cx - r, cy - r, r * 2, r * 2, 0, Some((a1, a2)), col));
+    }
+
+    fn stroke_circle(&mut self, x: i32, y: i32, w: i32, h: i32, line_thickness: i32, col: Color) {
+        self.shape(col, |s, col| s.ellipse(x, y, w, h, line_thickness.max(1), None, col));
+    }
+
+    fn fill_circle(&mut self, x: i32, y: i32, w: i32, h: i32, col: Color) {
+        self.shape(col, |s, col| s.ellipse(x, y, w, h, 0, None, col));
+    }
+
+    fn stroke_curve(&mut self, p1: Vec2i, p2: Vec2i, p3: Vec2i, p4: Vec2i, line_thickness: i32, col: Color) {
+        let (p1, p2, p3, p4) = ((p1.x as f32, p1.y as f32), (p2.x as f32, p2.y as f32), (p3.x as f32, p3.y as f32), (p4.x as f32, p4.y as f32));
+        let points: Vec<(f32, f32)> = (0..=CURVE_SEGMENTS)
+            .map(|i| {
+                let t = i as f32 / CURVE_SEGMENTS as f32;
+                let u = 1.0 - t;
+                let (w1, w2, w3, w4) = (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+                (w1 * p1.0 + w2 * p2.0 + w3 * p3.0 + w4 * p4.0, w1 * p1.1 + w2 * p2.1 + w3 * p3.1 + w4 * p4.1)
+            })
+            .collect();
+        self.shape(col, |s, col| s.stroke_path(&points, false, line_thickness, col));
+    }
+
+    // `col.a` fades the whole image, like `SourceConstantAlpha` on GDI
+    fn draw_image(&mut self, x: i32, y: i32, w: i32, h: i32, mut img: Image, col: Color) {
+        let index = img.id() as usize;
+        if index >= self.images.len() || w <= 0 || h <= 0 {
+            return;
+        }
+
+        for py in 0..h {
+            for px in 0..w {
+                let (iw, ih) = (self.images[index].width, self.images[index].height);
+                let (sx, sy) = ((px as u32 * iw) / w as u32, (py as u32 * ih) / h as u32);
+                let i = ((sy * iw + sx) * 4) as usize;
+                let p = &self.images[index].pixels[i..i + 4];
+                let src = Color { r: p[0], g: p[1], b: p[2], a: col.a };
+                let coverage = p[3];
+                self.blend(x + px, y + py, src, coverage);
+            }
+        }
+    }
+
+    // Clipped to its rectangle like `ETO_CLIPPED` on GDI.
+    fn draw_text(&mut self, x: i32, y: i32, w: i32, h: i32, text: &[u8], font: &UserFont, cbg: Color, cfg: Color) {
+        let text = String::from_utf8_lossy(text);
+        let nk_font = unsafe { &*(font as *const _ as *const nksys::nk_user_font) };
+        let height = nk_font.height as i32;
+
+        let glyphs: Vec<Glyph> = match self.glyph_source {
+            Some(ref source) => text.chars().filter_map(|c| source.glyph(font, c)).collect(),
+            None => Vec::new(),
+        };
+        let width = if self.glyph_source.is_some() {
+            glyphs.iter().map(|g| g.advance).sum()
+        } else {
+            match nk_font.width {
+                Some(width) => unsafe { width(nk_font.userdata, nk_font.height, text.as_ptr() as *const _, text.len() as i32) as i32 },
+                None => 0,
+            }
+        };
+
+        let clip = self.clip;
+        self.clip = (clip.0.max(x), clip.1.max(y), clip.2.min(x + w), clip.3.min(y + h));
+
+        self.shape(cbg, |s, col| {
+            for py in y..(y + height) {
+                s.span(x, x + width, py, col);
+            }
+        });
+
+        let mut pen = x;
+        for g in &glyphs {
+            for gy in 0..g.height as i32 {
+                for gx in 0..g.width as i32 {
+                    let coverage = g.coverage[(gy * g.width as i32 + gx) as usize];
+                    self.blend(pen + g.x_offset + gx, y + g.y_offset + gy, cfg, coverage);
+                }
+            }
+            pen += g.advance;
+        }
+        self.clip = clip;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLACK: Color = Color { r: 0, g: 0, b: 0, a: 255 };
+    const WHITE: Color = Color { r: 255, g: 255, b: 255, a: 255 };
+
+    fn surface() -> SoftwareSurface {
+        let mut surface = SoftwareSurface::new(16, 16);
+        surface.clear(BLACK);
+        surface
+    }
+
+    fn drawn(surface: &SoftwareSurface) -> Vec<(i32, i32)> {
+        let mut pixels = Vec::new();
+        for y in 0..surface.height() {
+            for x in 0..surface.width() {
+                if surface.pixel(x, y)[..3] != [0, 0, 0] {
+                    pixels.push((x, y));
+                }
+            }
+        }
+        pixels
+    }
+
+    fn boxed(l: i32, t: i32, r: i32, b: i32) -> Vec<(i32, i32)> {
+        (t..b).flat_map(|y| (l..r).map(move |x| (x, y))).collect()
+    }
+
+    fn font(height: f32) -> nksys::nk_user_font {
+        let mut font: nksys::nk_user_font = unsafe { ::std::mem::zeroed() };
+        font.height = height;
+        font
+    }
+
+    fn user_font(font: &nksys::nk_user_font) -> &UserFont {
+        unsafe { &*(font as *const nksys::nk_user_font as *const UserFont) }
+    }
+
+    #[test]
+    fn fill_rect_covers_its_half_open_box() {
+        let mut s = surface();
+        s.fill_rect(2, 3, 4, 5, 0, WHITE);
+        assert_eq!(drawn(&s), boxed(2, 3, 6, 8));
+    }
+
+    #[test]
+    fn fill_triangle_covers_its_outline_and_inside() {
+        let mut s = surface();
+        s.fill_triangle(0, 0, 10, 0, 0, 10, WHITE);
+        let pixels = drawn(&s);
+        for p in &[(0, 0), (9, 0), (0, 9), (3, 3), (5, 4)] {
+            assert!(pixels.contains(p), "{:?} not drawn", p);
+        }
+        for p in &[(7, 7), (10, 1), (1, 10), (11, 0)] {
+            assert!(!pixels.contains(p), "{:?} drawn", p);
+        }
+    }
+
+    #[test]
+    fn scissor_clips_to_its_box_until_cleared() {
+        let mut s = surface();
+        s.scissor(4, 5, 3, 2);
+        s.fill_rect(0, 0, 16, 16, 0, WHITE);
+        assert_eq!(drawn(&s), boxed(4, 5, 7, 7));
+
+        s.clear(BLACK);
+        s.fill_rect(0, 0, 16, 16, 0, WHITE);
+        assert_eq!(drawn(&s), boxed(0, 0, 16, 16));
+    }
+
+    #[test]
+    fn translucent_shapes_blend_once_and_transparent_ones_draw_nothing() {
+        let mut s = surface();
+        s.stroke_polyline(&[Vec2i { x: 2, y: 8 }, Vec2i { x: 8, y: 8 }, Vec2i { x: 8, y: 2 }], 3, Color { a: 128, ..WHITE });
+        assert_eq!(s.pixel(8, 8), [128, 128, 128, 255]);
+
+        let mut s = surface();
+        s.fill_rect(0, 0, 16, 16, 0, Color { a: 0, ..WHITE });
+        assert_eq!(drawn(&s), vec![]);
+    }
+
+    #[test]
+    fn text_is_visible_with_the_default_glyphs_and_clipped_to_its_rect() {
+        let font = font(8.0);
+        let mut s = surface();
+        s.draw_text(0, 0, 16, 8, b"a b", user_font(&font), Color { a: 0, ..BLACK }, WHITE);
+        // 4 pixel advance, 3 by 6 pixel boxes from row 2 down and nothing for the space; 'a' (0x61) fills the first inner row, 'b' (0x62) the second
+        let mut expected: Vec<(i32, i32)> = Vec::new();
+        for &left in &[0, 8] {
+            expected.extend(boxed(left, 2, left + 3, 8).into_iter().filter(|&(x, y)| x != left + 1 || y == 2 || y == 7));
+        }
+        expected.push((1, 3));
+        expected.push((9, 4));
+        expected.sort_by_key(|&(x, y)| (y, x));
+        assert_eq!(drawn(&s), expected);
+
+        let mut s = surface();
+        s.draw_text(0, 0, 5, 8, b"abc", user_font(&font), Color { a: 0, ..BLACK }, WHITE);
+        assert!(drawn(&s).iter().all(|&(x, _)| x < 5));
+        assert!(!drawn(&s).is_empty());
+    }
+}
@@ -0,0 +1,315 @@
+use crate::surface::Surface;
+use nuklear::*;
+use std::f32::consts::PI;
+
+/// How a frame is rasterized.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Quality {
+    /// Straight into the backbuffer, aliased.
+    Fast,
+    /// Into a `factor`× larger buffer (2 to 4) that is filtered down to the window size before it is shown.
+    /// Curves, circles, arcs and rounded corners come out smooth at roughly `factor²` times the fill cost.
+    Supersampled { factor: u8, filter: Filter },
+}
+
+impl Default for Quality {
+    fn default() -> Quality {
+        Quality::Fast
+    }
+}
+
+impl Quality {
+    /// 1 for `Fast`, otherwise the clamped supersampling factor.
+    pub fn factor(&self) -> i32 {
+        match *self {
+            Quality::Fast => 1,
+            Quality::Supersampled { factor, .. } => factor.max(2).min(4) as i32,
+        }
+    }
+}
+
+/// Downsampling filter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filter {
+    /// Plain average of each `factor`×`factor` block.
+    Box,
+    /// Three-lobe Lanczos; sharper edges, with slight ringing next to high-contrast ones.
+    Lanczos,
+}
+
+const LANCZOS_LOBES: f32 = 3.0;
+
+fn sinc(x: f32) -> f32 {
+    if x.abs() < 1e-6 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+// `d` is the distance from the output pixel center, in output pixels.
+fn weight(filter: Filter, d: f32) -> f32 {
+    match filter {
+        Filter::Box => {
+            if d.abs() < 0.5 {
+                1.0
+            } else {
+                0.0
+            }
+        }
+        Filter::Lanczos => {
+            if d.abs() < LANCZOS_LOBES {
+                sinc(d) * sinc(d / LANCZOS_LOBES)
+            } else {
+                0.0
+            }
+        }
+    }
+}
+
+// For every output pixel, the first source pixel and the normalized weights of the source pixels from there on.
+fn taps(filter: Filter, factor: i32, len: usize) -> Vec<(usize, Vec<f32>)> {
+    let src_len = len * factor as usize;
+    let f = factor as f32;
+    let support = match filter {
+        Filter::Box => 0.5,
+        Filter::Lanczos => LANCZOS_LOBES,
+    };
+
+    (0..len)
+        .map(|o| {
+            let center = (o as f32 + 0.5) * f;
+            let first = (center - support * f).floor().max(0.0) as usize;
+            let last = ((center + support * f).ceil() as usize).min(src_len);
+            let mut weights: Vec<f32> = (first..last).map(|i| weight(filter, (i as f32 + 0.5 - center) / f)).collect();
+            let sum: f32 = weights.iter().sum();
+            if sum > 0.0 {
+                for w in &mut weights {
+                    *w /= sum;
+                }
+            }
+            (first, weights)
+        })
+        .collect()
+}
+
+/// Filters a 4-channel image of `width * factor` by `height * factor` pixels down to `width` by `height`.
+/// Channels are treated alike, so both RGBA and BGRA work.
+pub fn downsample(src: &[u8], width: usize, height: usize, factor: i32, filter: Filter) -> Vec<u8> {
+    let src_width = width * factor as usize;
+    let src_height = height * factor as usize;
+    let columns = taps(filter, factor, width);
+    let rows = taps(filter, factor, height);
+
+    // horizontal pass: width x src_height
+    let mut tmp = vec![0f32; width * src_height * 4];
+    for y in 0..src_height {
+        let line = &src[y * src_width * 4..(y + 1) * src_width * 4];
+        for (x, &(first, ref weights)) in columns.iter().enumerate() {
+            let out = &mut tmp[(y * width + x) * 4..(y * width + x + 1) * 4];
+            for (k, w) in weights.iter().enumerate() {
+                let p = &line[(first + k) * 4..(first + k + 1) * 4];
+                for (o, v) in out.iter_mut().zip(p) {
+                    *o += *v as f32 * w;
+                }
+            }
+        }
+    }
+
+    let mut dst = vec![0u8; width * height * 4];
+    for (y, &(first, ref weights)) in rows.iter().enumerate() {
+        for x in 0..width {
+            let mut acc = [0f32; 4];
+            for (k, w) in weights.iter().enumerate() {
+                let p = &tmp[((first + k) * width + x) * 4..((first + k) * width + x + 1) * 4];
+                for (a, v) in acc.iter_mut().zip(p) {
+                    *a += v * w;
+                }
+            }
+            for (d, a) in dst[(y * width + x) * 4..(y * width + x + 1) * 4].iter_mut().zip(&acc) {
+                *d = a.round().max(0.0).min(255.0) as u8;
+            }
+        }
+    }
+    dst
+}
+
+/// Forwards to another surface with every coordinate, size, radius and line thickness multiplied by `factor`.
+///
+/// Text keeps its font: the target has to scale glyphs itself (`GdiSurface::set_scale` does on Windows).
+/// Polygon, polyline and curve points stay 16-bit, so scaled coordinates beyond the `i16` range are clamped to it.
+pub struct Scaled<'a, S: Surface + ?Sized> {
+    inner: &'a mut S,
+    factor: i32,
+}
+
+impl<'a, S: Surface + ?Sized> Scaled<'a, S> {
+    pub fn new(inner: &'a mut S, factor: i32) -> Scaled<'a, S> {
+        Scaled { inner: inner, factor: factor }
+    }
+
+    fn point(&self, p: Vec2i) -> Vec2i {
+        let scale = |v: i16| (v as i32 * self.factor).max(i16::min_value() as i32).min(i16::max_value() as i32) as i16;
+        Vec2i { x: scale(p.x), y: scale(p.y) }
+    }
+
+    fn points(&self, points: &[Vec2i]) -> Vec<Vec2i> {
+        points.iter().map(|&p| self.point(p)).collect()
+    }
+}
+
+impl<'a, S: Surface + ?Sized> Surface for Scaled<'a, S> {
+    fn begin_command(&mut self) {
+        self.inner.begin_command();
+    }
+
+    fn clear(&mut self, col: Color) {
+        self.inner.clear(col);
+    }
+
+    fn scissor(&mut self, x: i32, y: i32, w: i32, h: i32) {
+        let f = self.factor;
+        self.inner.scissor(x * f, y * f, w * f, h * f);
+    }
+
+    fn stroke_line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, line_thickness: i32, col: Color) {
+        let f = self.factor;
+        self.inner.stroke_line(x0 * f, y0 * f, x1 * f, y1 * f, line_thickness * f, col);
+    }
+
+    fn stroke_rect(&mut self, x: i32, y: i32, w: i32, h: i32, r: i32, line_thickness: i32, col: Color) {
+        let f = self.factor;
+        self.inner.stroke_rect(x * f, y * f, w * f, h * f, r * f, line_thickness * f, col);
+    }
+
+    fn fill_rect(&mut self, x: i32, y: i32, w: i32, h: i32, r: i32, col: Color) {
+        let f = self.factor;
+        self.inner.fill_rect(x * f, y * f, w * f, h * f, r * f, col);
+    }
+
+    fn fill_rect_multi_color(&mut self, x: i32, y: i32, w: i32, h: i32, left: Color, top: Color, right: Color, bottom: Color) {
+        let f = self.factor;
+        self.inner.fill_rect_multi_color(x * f, y * f, w * f, h * f, left, top, right, bottom);
+    }
+
+    fn stroke_triangle(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, x2: i32, y2: i32, line_thickness: i32, col: Color) {
+        let f = self.factor;
+        self.inner.stroke_triangle(x0 * f, y0 * f, x1 * f, y1 * f, x2 * f, y2 * f, line_thickness * f, col);
+    }
+
+    fn fill_triangle(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, x2: i32, y2: i32, col: Color) {
+        let f = self.factor;
+        self.inner.fill_triangle(x0 * f, y0 * f, x1 * f, y1 * f, x2 * f, y2 * f, col);
+    }
+
+    fn stroke_polygon(&mut self, points: &[Vec2i], line_thickness: i32, col: Color) {
+        let points = self.points(points);
+        self.inner.stroke_polygon(&points, line_thickness * self.factor, col);
+    }
+
+    fn fill_polygon(&mut self, points: &[Vec2i], col: Color) {
+        let points = self.points(points);
+        self.inner.fill_polygon(&points, col);
+    }
+
+    fn stroke_polyline(&mut self, points: &[Vec2i], line_thickness: i32, col: Color) {
+        let points = self.points(points);
+        self.inner.stroke_polyline(&points, line_thickness * self.factor, col);
+    }
+
+    fn stroke_arc(&mut self, cx: i32, cy: i32, r: u32, a1: f32, a2: f32, line_thickness: i32, col: Color) {
+        let f = self.factor;
+        self.inner.stroke_arc(cx * f, cy * f, r * f as u32, a1, a2, line_thickness * f, col);
+    }
+
+    fn fill_arc(&mut self, cx: i32, cy: i32, r: u32, a1: f32, a2: f32, col: Color) {
+        let f = self.factor;
+        self.inner.fill_arc(cx * f, cy * f, r * f as u32, a1, a2, col);
+    }
+
+    fn stroke_circle(&mut self, x: i32, y: i32, w: i32, h: i32, line_thickness: i32, col: Color) {
+        let f = self.factor;
+        self.inner.stroke_circle(x * f, y * f, w * f, h * f, line_thickness * f, col);
+    }
+
+    fn fill_circle(&mut self, x: i32, y: i32, w: i32, h: i32, col: Color) {
+        let f = self.factor;
+        self.inner.fill_circle(x * f, y * f, w * f, h * f, col);
+    }
+
+    fn stroke_curve(&mut self, p1: Vec2i, p2: Vec2i, p3: Vec2i, p4: Vec2i, line_thickness: i32, col: Color) {
+        let (p1, p2, p3, p4) = (self.point(p1), self.point(p2), self.point(p3), self.point(p4));
+        self.inner.stroke_curve(p1, p2, p3, p4, line_thickness * self.factor, col);
+    }
+
+    fn draw_image(&mut self, x: i32, y: i32, w: i32, h: i32, img: Image, col: Color) {
+        let f = self.factor;
+        self.inner.draw_image(x * f, y * f, w * f, h * f, img, col);
+    }
+
+    fn draw_text(&mut self, x: i32, y: i32, w: i32, h: i32, text: &[u8], font: &UserFont, cbg: Color, cfg: Color) {
+        let f = self.factor;
+        self.inner.draw_text(x * f, y * f, w * f, h * f, text, font, cbg, cfg);
+    }
+
+    fn draw_custom(&mut self, x: i32, y: i32, w: i32, h: i32, data: Handle) {
+        let f = self.factor;
+        self.inner.draw_custom(x * f, y * f, w * f, h * f, data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recording::{Primitive, RecordingSurface};
+
+    #[test]
+    fn taps_are_normalized_and_stay_inside_the_source() {
+        for &filter in &[Filter::Box, Filter::Lanczos] {
+            for factor in 2..=4 {
+                for (first, weights) in taps(filter, factor, 5) {
+                    assert!((weights.iter().sum::<f32>() - 1.0).abs() < 1e-4);
+                    assert!(first + weights.len() <= 5 * factor as usize);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn box_taps_cover_exactly_their_block() {
+        let taps = taps(Filter::Box, 2, 3);
+        assert_eq!(taps, vec![(0, vec![0.5, 0.5]), (2, vec![0.5, 0.5]), (4, vec![0.5, 0.5])]);
+    }
+
+    #[test]
+    fn uniform_input_stays_uniform() {
+        let src: Vec<u8> = [10u8, 20, 30, 255].iter().cloned().cycle().take(6 * 6 * 4).collect();
+        for &filter in &[Filter::Box, Filter::Lanczos] {
+            let dst = downsample(&src, 2, 2, 3, filter);
+            assert_eq!(dst, [10u8, 20, 30, 255].iter().cloned().cycle().take(2 * 2 * 4).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn box_filter_averages_each_block() {
+        // four pixels, each lighting a different channel
+        let src = [0u8, 0, 0, 0, 100, 0, 0, 0, 0, 200, 0, 0, 0, 0, 255, 255];
+        assert_eq!(downsample(&src, 1, 1, 2, Filter::Box), vec![25, 50, 64, 64]);
+    }
+
+    #[test]
+    fn scaled_multiplies_geometry_and_clamps_points() {
+        let mut surface = RecordingSurface::new();
+        {
+            let mut scaled = Scaled::new(&mut surface, 4);
+            scaled.stroke_rect(1, 2, 3, 4, 1, 2, Color { r: 0, g: 0, b: 0, a: 255 });
+            scaled.stroke_polyline(&[Vec2i { x: 10, y: -10 }, Vec2i { x: 10_000, y: -10_000 }], 1, Color { r: 0, g: 0, b: 0, a: 255 });
+        }
+
+        let records = surface.take_records();
+        assert_eq!(records[0].primitive, Primitive::Rect { x: 4, y: 8, w: 12, h: 16, rounding: 4, filled: false });
+        assert_eq!(records[0].line_thickness, 8);
+        assert_eq!(records[1].primitive, Primitive::Polyline { points: vec![(40, -40), (32767, -32768)] });
+        assert_eq!(records[1].line_thickness, 4);
+    }
+}
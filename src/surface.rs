@@ -0,0 +1,187 @@
+use nuklear::nuklear_sys as nksys;
+use nuklear::*;
+use std::slice;
+
+/// A drawing target for the nuklear command stream.
+///
+/// `draw_commands` translates every command into calls on this trait, so the same translation runs against GDI, an in-memory raster or a test double.
+/// Colors carry straight alpha: a primitive with `a < 255` is composited over what is already drawn and one with `a == 0` draws nothing.
+///
+/// Pixel coverage follows one rule for every primitive. Coordinates are whole pixels, taken unchanged from nuklear's integer command fields.
+/// A box given as `x, y, w, h` (rects, circles, images, text, scissors) covers the columns `x..x + w` and the rows `y..y + h`,
+/// the right and bottom edge excluded as in GDI, so boxes that share an edge neither overlap nor leave a seam.
+/// A one pixel outline of a box lies on its outermost pixels; thicker outlines are centered on that ring. Lines and paths join the
+/// given points and, like `LineTo`, leave out the last pixel of an open line.
+pub trait Surface {
+    /// Called before each command is dispatched.
+    fn begin_command(&mut self) {}
+
+    fn clear(&mut self, col: Color);
+    /// Limits drawing to `x..x + w` by `y..y + h` until the next scissor.
+    fn scissor(&mut self, x: i32, y: i32, w: i32, h: i32);
+    fn stroke_line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, line_thickness: i32, col: Color);
+    fn stroke_rect(&mut self, x: i32, y: i32, w: i32, h: i32, r: i32, line_thickness: i32, col: Color);
+    fn fill_rect(&mut self, x: i32, y: i32, w: i32, h: i32, r: i32, col: Color);
+    /// Four-corner gradient; nuklear names the corners `left` (top-left), `top` (top-right), `right` (bottom-right) and `bottom` (bottom-left).
+    fn fill_rect_multi_color(&mut self, x: i32, y: i32, w: i32, h: i32, left: Color, top: Color, right: Color, bottom: Color);
+    fn stroke_triangle(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, x2: i32, y2: i32, line_thickness: i32, col: Color);
+    fn fill_triangle(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, x2: i32, y2: i32, col: Color);
+    fn stroke_polygon(&mut self, points: &[Vec2i], line_thickness: i32, col: Color);
+    fn fill_polygon(&mut self, points: &[Vec2i], col: Color);
+    fn stroke_polyline(&mut self, points: &[Vec2i], line_thickness: i32, col: Color);
+    fn stroke_arc(&mut self, cx: i32, cy: i32, r: u32, a1: f32, a2: f32, line_thickness: i32, col: Color);
+    fn fill_arc(&mut self, cx: i32, cy: i32, r: u32, a1: f32, a2: f32, col: Color);
+    fn stroke_circle(&mut self, x: i32, y: i32, w: i32, h: i32, line_thickness: i32, col: Color);
+    fn fill_circle(&mut self, x: i32, y: i32, w: i32, h: i32, col: Color);
+    fn stroke_curve(&mut self, p1: Vec2i, p2: Vec2i, p3: Vec2i, p4: Vec2i, line_thickness: i32, col: Color);
+    fn draw_image(&mut self, x: i32, y: i32, w: i32, h: i32, img: Image, col: Color);
+    fn draw_text(&mut self, x: i32, y: i32, w: i32, h: i32, text: &[u8], font: &UserFont, cbg: Color, cfg: Color);
+    /// A `nk_push_custom` command; `data` is the user handle it was pushed with. Ignored unless the surface supports it.
+    fn draw_custom(&mut self, _x: i32, _y: i32, _w: i32, _h: i32, _data: Handle) {}
+}
+
+/// How polygons with self-intersections are filled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FillRule {
+    /// Even-odd: a region is filled when a ray from it crosses the outline an odd number of times (GDI `ALTERNATE`).
+    Alternate,
+    /// Nonzero winding: every region the outline winds around is filled (GDI `WINDING`).
+    Winding,
+}
+
+impl Default for FillRule {
+    fn default() -> FillRule {
+        FillRule::Alternate
+    }
+}
+
+impl FillRule {
+    /// Whether a point with the given winding number is inside.
+    pub fn contains(self, winding: i32) -> bool {
+        match self {
+            FillRule::Alternate => winding % 2 != 0,
+            FillRule::Winding => winding != 0,
+        }
+    }
+}
+
+/// GDI's default miter limit: the longest miter, from the inner to the outer corner, in line widths. A miter join therefore reaches
+/// at most `MITER_LIMIT` half widths past its vertex.
+pub const MITER_LIMIT: f32 = 10.0;
+
+/// How thick stroke segments meet. Miter joins longer than `MITER_LIMIT` line widths fall back to bevels, as in GDI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineJoin {
+    Miter,
+    Bevel,
+    Round,
+}
+
+/// How the ends of open thick strokes look.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineCap {
+    /// Ends exactly at the end point.
+    Flat,
+    /// Extends half the line width past the end point.
+    Square,
+    Round,
+}
+
+/// Corner radius of a rounded rectangle as nuklear defines it: `rounding` is the radius, capped at half the shorter side.
+pub fn corner_radius(w: i32, h: i32, rounding: i32) -> i32 {
+    rounding.min(w / 2).min(h / 2).max(0)
+}
+
+/// Packs a nuklear color into a GDI `COLORREF` (`0x00BBGGRR`), dropping alpha.
+pub fn convert_color(c: Color) -> u32 {
+    c.r as u32 | ((c.g as u32) << 8) | ((c.b as u32) << 16)
+}
+
+/// Feeds every command queued in `ctx` into `surface`. The context is left untouched.
+pub fn draw_commands<S: Surface + ?Sized>(surface: &mut S, ctx: &mut Context) {
+    for cmd in ctx.command_iterator() {
+        draw_command(surface, cmd);
+    }
+}
+
+pub fn draw_command<S: Surface + ?Sized>(surface: &mut S, cmd: &Command) {
+    surface.begin_command();
+
+    match cmd.get_type() {
+        CommandType::ArcFilled => {
+            let a: &CommandArcFilled = cmd.as_ref();
+            surface.fill_arc(a.cx() as i32, a.cy() as i32, a.r() as u32, a.a()[0], a.a()[1], a.color());
+        }
+        CommandType::Arc => {
+            let a: &CommandArc = cmd.as_ref();
+            surface.stroke_arc(a.cx() as i32, a.cy() as i32, a.r() as u32, a.a()[0], a.a()[1], a.line_thickness() as i32, a.color());
+        }
+        CommandType::Scissor => {
+            let s: &CommandScissor = cmd.as_ref();
+            surface.scissor(s.x() as i32, s.y() as i32, s.w() as i32, s.h() as i32);
+        }
+        CommandType::Line => {
+            let l: &CommandLine = cmd.as_ref();
+            surface.stroke_line(l.begin().x as i32, l.begin().y as i32, l.end().x as i32, l.end().y as i32, l.line_thickness() as i32, l.color());
+        }
+        CommandType::Rect => {
+            let r: &CommandRect = cmd.as_ref();
+            surface.stroke_rect(r.x() as i32, r.y() as i32, r.w() as i32, r.h() as i32, r.rounding() as u16 as i32, r.line_thickness() as i32, r.color());
+        }
+        CommandType::RectFilled => {
+            let r: &CommandRectFilled = cmd.as_ref();
+            surface.fill_rect(r.x() as i32, r.y() as i32, r.w() as i32, r.h() as i32, r.rounding() as u16 as i32, r.color());
+        }
+        CommandType::RectMultiColor => {
+            let r: &CommandRectMultiColor = cmd.as_ref();
+            surface.fill_rect_multi_color(r.x() as i32, r.y() as i32, r.w() as i32, r.h() as i32, r.left(), r.top(), r.right(), r.bottom());
+        }
+        CommandType::Circle => {
+            let c: &CommandCircle = cmd.as_ref();
+            surface.stroke_circle(c.x() as i32, c.y() as i32, c.w() as i32, c.h() as i32, c.line_thickness() as i32, c.color());
+        }
+        CommandType::CircleFilled => {
+            let c: &CommandCircleFilled = cmd.as_ref();
+            surface.fill_circle(c.x() as i32, c.y() as i32, c.w() as i32, c.h() as i32, c.color());
+        }
+        CommandType::Triangle => {
+            let t: &CommandTriangle = cmd.as_ref();
+            surface.stroke_triangle(t.a().x as i32, t.a().y as i32, t.b().x as i32, t.b().y as i32, t.c().x as i32, t.c().y as i32, t.line_thickness() as i32, t.color());
+        }
+        CommandType::TriangleFilled => {
+            let t: &CommandTriangleFilled = cmd.as_ref();
+            surface.fill_triangle(t.a().x as i32, t.a().y as i32, t.b().x as i32, t.b().y as i32, t.c().x as i32, t.c().y as i32, t.color());
+        }
+        CommandType::Polygon => {
+            let p: &CommandPolygon = cmd.as_ref();
+            surface.stroke_polygon(p.points(), p.line_thickness() as i32, p.color());
+        }
+        CommandType::PolygonFilled => {
+            let p: &CommandPolygonFilled = cmd.as_ref();
+            surface.fill_polygon(p.points(), p.color());
+        }
+        CommandType::Polyline => {
+            let p: &CommandPolyline = cmd.as_ref();
+            surface.stroke_polyline(p.points(), p.line_thickness() as i32, p.color());
+        }
+        CommandType::Text => {
+            let t: &CommandText = cmd.as_ref();
+            let text = unsafe { slice::from_raw_parts(t.chars().as_ptr() as *const u8, t.chars().len()) };
+            surface.draw_text(t.x() as i32, t.y() as i32, t.w() as i32, t.h() as i32, text, t.font(), t.background(), t.foreground());
+        }
+        CommandType::Curve => {
+            let q: &CommandCurve = cmd.as_ref();
+            surface.stroke_curve(q.begin(), q.ctrl()[0], q.ctrl()[1], q.end(), q.line_thickness() as i32, q.color());
+        }
+        CommandType::Image => {
+            let i: &CommandImage = cmd.as_ref();
+            surface.draw_image(i.x() as i32, i.y() as i32, i.w() as i32, i.h() as i32, i.img(), i.col());
+        }
+        CommandType::Custom => {
+            let c = unsafe { &*(cmd as *const _ as *const nksys::nk_command_custom) };
+            let data = unsafe { Handle::from_ptr(c.callback_data.ptr) };
+            surface.draw_custom(c.x as i32, c.y as i32, c.w as i32, c.h as i32, data);
+        }
+        _ => {}
+    }
+}